@@ -0,0 +1,98 @@
+// GitHub responses kept on disk between runs, keyed by url. Requests send the
+// saved ETag and a 304 reuses the saved body, such requests are faster and
+// GitHub doesn't count them against the rate limit. See --no-cache.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::error::StandupError;
+
+#[derive(Serialize, Deserialize)]
+pub struct CachedResponse {
+    // guards against collisions of file names
+    pub url: String,
+    pub etag: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+// FNV-1a, file names must be the same between runs and builds
+fn hash(v: &str) -> u64 {
+    v.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Cache {
+        Cache { dir }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", hash(url)))
+    }
+
+    // unreadable or broken entries are treated as missing
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let json = fs::read_to_string(self.path(url)).ok()?;
+        match serde_json::from_str::<CachedResponse>(&json) {
+            Ok(cached) if cached.url == url => Some(cached),
+            Ok(_) => None,
+            Err(e) => {
+                debug!("ignoring broken cache entry of {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, cached: &CachedResponse) -> Result<(), StandupError> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| StandupError::io("can not create cache directory", e))?;
+        let json = serde_json::to_string(cached)?;
+        fs::write(self.path(&cached.url), json)
+            .map_err(|e| StandupError::io("can not write cache entry", e))
+    }
+
+    pub fn clear(dir: &Path) -> Result<(), StandupError> {
+        if !dir.exists() {
+            return Ok(());
+        }
+        fs::remove_dir_all(dir).map_err(|e| StandupError::io("can not remove cache", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_responses_by_url() {
+        let dir = std::env::temp_dir().join(format!("standup-cache-test-{}", std::process::id()));
+        let cache = Cache::new(dir.clone());
+        let url = "https://api.github.com/repos/org/a";
+        assert!(cache.get(url).is_none());
+
+        cache
+            .put(&CachedResponse {
+                url: String::from(url),
+                etag: String::from("\"abc\""),
+                headers: vec![(String::from("link"), String::from("<next>"))],
+                body: String::from("{}"),
+            })
+            .unwrap();
+        let cached = cache.get(url).unwrap();
+        assert_eq!(cached.etag, "\"abc\"");
+        assert_eq!(cached.body, "{}");
+        assert!(cache.get("https://api.github.com/repos/org/b").is_none());
+
+        Cache::clear(&dir).unwrap();
+        assert!(cache.get(url).is_none());
+    }
+}
@@ -0,0 +1,29 @@
+use chrono::prelude::*;
+
+// source of "now" for everything relative to the current time,
+// tests pin it to keep keywords like yesterday deterministic
+pub trait Clock {
+    fn now(&self) -> DateTime<Local>;
+
+    fn today(&self) -> Date<Local> {
+        self.now().date()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+#[cfg(test)]
+pub struct FixedClock(pub DateTime<Local>);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
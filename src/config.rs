@@ -1,14 +1,32 @@
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::error::StandupError;
+
 #[derive(Serialize, Deserialize)]
 pub struct Github {
     pub username: String,
     pub token: String,
+    // other logins treated as the user
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    // always report Github Projects items, same as --include-projects
+    #[serde(default)]
+    pub include_projects: bool,
+    // tried when the token can't access a repository, e.g. orgs with SSO
+    #[serde(default)]
+    pub extra_tokens: Vec<String>,
+    // PRs merged into these branches are reported as deployed, e.g. ["production"]
+    #[serde(default)]
+    pub deploy_branches: Vec<String>,
+    // REST API of GitHub Enterprise Server, e.g. https://github.example.com/api/v3
+    #[serde(default)]
+    pub api_url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -17,7 +35,7 @@ pub struct GoogleClient {
     pub client_secret: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GoogleToken {
     pub access_token: String,
     pub refresh_token: String,
@@ -29,41 +47,214 @@ pub struct GoogleCalendar {
     pub id: String,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct Sprint {
+    // first day of any sprint, others are computed from it
+    pub start: NaiveDate,
+    pub length_days: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WorkHours {
+    // local time, e.g. "09:00:00", end before start spans midnight
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub github: Github,
     pub google_client: Option<GoogleClient>,
     pub google_token: Option<GoogleToken>,
     pub gcal: Option<GoogleCalendar>,
+    #[serde(default)]
+    pub sprint: Option<Sprint>,
+    // entries with these actions go first within a repository, e.g. ["authored & merged", "reviewed"]
+    #[serde(default)]
+    pub action_priority: Vec<String>,
+    // notes added with --interactive keyed by url of PR or issue
+    #[serde(default)]
+    pub notes: HashMap<String, String>,
+    // short names to show instead of full repository names, e.g. {"org/really-long-repo-name": "repo"}
+    #[serde(default)]
+    pub repo_aliases: HashMap<String, String>,
+    // proxy for all requests, e.g. http://proxy.example.com:3128, HTTPS_PROXY and HTTP_PROXY are used if not set
+    #[serde(default)]
+    pub proxy: Option<String>,
+    // sent with every request instead of standup-rs/<version>
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    // activity outside these hours is dropped with --work-hours-only
+    #[serde(default)]
+    pub work_hours: Option<WorkHours>,
 }
 
+// replaces secrets in exported configs
+pub const REDACTED: &str = "<redacted>";
+
 impl Config {
-    pub fn load(file_path: &Path) -> Result<Option<Config>, String> {
+    pub fn redact_secrets(&mut self) {
+        self.github.token = String::from(REDACTED);
+        for t in self.github.extra_tokens.iter_mut() {
+            *t = String::from(REDACTED);
+        }
+        if let Some(client) = &mut self.google_client {
+            client.client_secret = String::from(REDACTED);
+        }
+        if let Some(token) = &mut self.google_token {
+            token.access_token = String::from(REDACTED);
+            token.refresh_token = String::from(REDACTED);
+        }
+    }
+
+    pub fn has_redacted_secrets(&self) -> bool {
+        let client_secret = self
+            .google_client
+            .as_ref()
+            .map(|c| c.client_secret.as_str());
+        let tokens = self
+            .google_token
+            .iter()
+            .flat_map(|t| vec![t.access_token.as_str(), t.refresh_token.as_str()]);
+        std::iter::once(self.github.token.as_str())
+            .chain(self.github.extra_tokens.iter().map(String::as_str))
+            .chain(client_secret)
+            .chain(tokens)
+            .any(|v| v == REDACTED)
+    }
+
+    // fills redacted secrets from the current config, extra tokens can't be matched
+    // one by one, so they are taken only if all of them are redacted
+    pub fn restore_secrets(&mut self, from: &Config) {
+        if self.github.token == REDACTED {
+            self.github.token = from.github.token.clone();
+        }
+        if !self.github.extra_tokens.is_empty()
+            && self.github.extra_tokens.iter().all(|t| t == REDACTED)
+        {
+            self.github.extra_tokens = from.github.extra_tokens.clone();
+        }
+        let same_client = matches!(
+            (&self.google_client, &from.google_client),
+            (Some(client), Some(from)) if client.client_id == from.client_id
+        );
+        if let (Some(client), Some(from)) = (&mut self.google_client, &from.google_client) {
+            if client.client_secret == REDACTED && same_client {
+                client.client_secret = from.client_secret.clone();
+            }
+        }
+        let redacted = self
+            .google_token
+            .as_ref()
+            .is_some_and(|t| t.access_token == REDACTED || t.refresh_token == REDACTED);
+        // tokens of another client are rejected, the calendar is authorized again instead
+        if redacted {
+            self.google_token = from.google_token.clone().filter(|_| same_client);
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, StandupError> {
+        Ok(serde_json::to_string_pretty(&self)?)
+    }
+
+    // a file that isn't a valid config is reported as Parse
+    pub fn load(file_path: &Path) -> Result<Option<Config>, StandupError> {
         if !file_path.exists() {
             return Ok(None);
         }
 
-        let mut file = File::open(&file_path).map_err(|e| format!("can not open file: {}", e))?;
+        let mut file =
+            File::open(file_path).map_err(|e| StandupError::io("can not open file", e))?;
         let mut json = String::new();
         file.read_to_string(&mut json)
-            .map_err(|e| format!("can not read file: {}", e))?;
+            .map_err(|e| StandupError::io("can not read file", e))?;
 
-        let cfg: Config =
-            serde_json::from_str(&json).map_err(|e| format!("can not deserialize file: {}", e))?;
+        let cfg: Config = serde_json::from_str(&json)?;
 
         Ok(Some(cfg))
     }
 
-    pub fn save(&self, file_path: &PathBuf) -> Result<(), String> {
-        let json = serde_json::to_string_pretty(&self)
-            .map_err(|e| format!("can not serialize config file: {}", e))?;
+    // moves the file aside as <name>.bak, an older backup is overwritten
+    pub fn backup(file_path: &Path) -> Result<PathBuf, StandupError> {
+        let mut backup = file_path.as_os_str().to_owned();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
+        fs::rename(file_path, &backup)
+            .map_err(|e| StandupError::io("can not back up config file", e))?;
+        Ok(backup)
+    }
+
+    pub fn save(&self, file_path: &PathBuf) -> Result<(), StandupError> {
+        let json = self.to_json()?;
 
         let path = Path::new(&file_path);
         let mut file =
-            File::create(&path).map_err(|e| format!("can not open config file: {}", e))?;
+            File::create(path).map_err(|e| StandupError::io("can not open config file", e))?;
         file.write_all(json.as_bytes())
-            .map_err(|e| format!("can not write config file: {}", e))?;
+            .map_err(|e| StandupError::io("can not write config file", e))?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra_tokens: &[&str]) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "github": { "username": "me", "token": "token", "extra_tokens": extra_tokens },
+            "google_client": null,
+            "google_token": null,
+            "gcal": null,
+        }))
+        .unwrap()
+    }
+
+    fn with_google(mut cfg: Config, client_id: &str) -> Config {
+        cfg.google_client = Some(GoogleClient {
+            client_id: String::from(client_id),
+            client_secret: String::from("secret"),
+        });
+        cfg.google_token = Some(GoogleToken {
+            access_token: String::from("access"),
+            refresh_token: String::from("refresh"),
+            experies_at: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+        });
+        cfg
+    }
+
+    #[test]
+    fn restores_google_secrets_of_the_same_client() {
+        let current = with_google(config(&[]), "client");
+
+        let mut imported = with_google(config(&[]), "client");
+        imported.redact_secrets();
+        imported.restore_secrets(&current);
+        assert_eq!(imported.google_client.unwrap().client_secret, "secret");
+        assert_eq!(imported.google_token.unwrap().refresh_token, "refresh");
+
+        let mut imported = with_google(config(&[]), "other-client");
+        imported.redact_secrets();
+        imported.restore_secrets(&current);
+        assert_eq!(imported.google_client.unwrap().client_secret, REDACTED);
+        assert!(imported.google_token.is_none());
+    }
+
+    #[test]
+    fn restores_redacted_github_tokens() {
+        let current = config(&["sso-token"]);
+
+        let mut imported = config(&["sso-token"]);
+        imported.redact_secrets();
+        imported.restore_secrets(&current);
+        assert_eq!(imported.github.token, "token");
+        assert_eq!(imported.github.extra_tokens, vec!["sso-token"]);
+
+        // a backup without extra tokens doesn't inherit them
+        let mut imported = config(&[]);
+        imported.redact_secrets();
+        imported.restore_secrets(&current);
+        assert!(imported.github.extra_tokens.is_empty());
+    }
+}
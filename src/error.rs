@@ -0,0 +1,71 @@
+// Errors of GitHub, Google Calendar and config handling. Variants keep the
+// underlying error, so callers can tell a rejected token from a network failure.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum StandupError {
+    // request couldn't be sent or got an unexpected status
+    Http(reqwest::Error),
+    // response or file isn't the expected json
+    Parse(serde_json::Error),
+    // config, progress and other local files
+    Config(io::Error),
+    // token is missing, expired or lacks permissions
+    Auth(String),
+    // the API answered but reported an error, e.g. in a GraphQL response
+    Api(String),
+    // a value from the config or command line can't be used, e.g. a proxy url
+    Invalid(String),
+}
+
+impl StandupError {
+    // io errors don't mention the file, the message keeps what was being done
+    pub fn io(context: &str, e: io::Error) -> StandupError {
+        StandupError::Config(io::Error::new(e.kind(), format!("{}: {}", context, e)))
+    }
+}
+
+impl fmt::Display for StandupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StandupError::Http(e) => write!(f, "request failed: {}", e),
+            StandupError::Parse(e) => write!(f, "invalid json: {}", e),
+            StandupError::Config(e) => write!(f, "{}", e),
+            StandupError::Auth(e) | StandupError::Api(e) | StandupError::Invalid(e) => {
+                write!(f, "{}", e)
+            }
+        }
+    }
+}
+
+impl Error for StandupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StandupError::Http(e) => Some(e),
+            StandupError::Parse(e) => Some(e),
+            StandupError::Config(e) => Some(e),
+            StandupError::Auth(_) | StandupError::Api(_) | StandupError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for StandupError {
+    fn from(e: reqwest::Error) -> StandupError {
+        StandupError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for StandupError {
+    fn from(e: serde_json::Error) -> StandupError {
+        StandupError::Parse(e)
+    }
+}
+
+impl From<io::Error> for StandupError {
+    fn from(e: io::Error) -> StandupError {
+        StandupError::Config(e)
+    }
+}
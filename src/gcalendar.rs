@@ -1,19 +1,24 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::io::{BufRead, BufReader, Write};
 use std::net::TcpListener;
 
 use chrono::prelude::*;
+use log::{debug, info};
 // oauth2 v3 crate api is awful but v1 doesn't handle errors from the server properly
 use oauth2::basic::BasicClient;
-use oauth2::reqwest::http_client;
 use oauth2::{
     AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, ExtraTokenFields, RedirectUrl,
     RefreshToken, ResponseType, Scope, StandardTokenResponse, TokenResponse, TokenType, TokenUrl,
 };
+use reqwest::StatusCode;
 use serde::Deserialize;
 use time::Duration;
 use url::Url;
 
 use crate::config::{Config, GoogleToken};
+use crate::error::StandupError;
+use crate::http;
 use crate::report::*;
 
 // Google calendar structs
@@ -34,20 +39,87 @@ struct EventsResp {
     items: Vec<Event>,
 }
 
+#[derive(Deserialize)]
+struct EventTime {
+    #[serde(rename = "dateTime")]
+    date_time: Option<DateTime<FixedOffset>>,
+    // all-day events have only a date
+    date: Option<NaiveDate>,
+}
+
+impl EventTime {
+    fn instant(&self) -> Option<DateTime<Utc>> {
+        match (self.date_time, self.date) {
+            (Some(d), _) => Some(d.with_timezone(&Utc)),
+            (None, Some(d)) => Local
+                .from_local_date(&d)
+                .earliest()
+                .map(|d| d.and_hms(0, 0, 0).with_timezone(&Utc)),
+            (None, None) => None,
+        }
+    }
+}
+
+// 09:00–09:30 in local time, all-day events have only dates
+fn time_range(start: Option<&EventTime>, end: Option<&EventTime>) -> Option<String> {
+    let local = |t: &EventTime| {
+        t.date_time
+            .map(|d| d.with_timezone(&Local).format("%H:%M").to_string())
+    };
+    match (start.and_then(local), end.and_then(local)) {
+        (Some(s), Some(e)) => Some(format!("{}–{}", s, e)),
+        (Some(s), None) => Some(s),
+        (None, _) => start.and_then(|t| t.date).map(|_| String::from("all day")),
+    }
+}
+
 #[derive(Deserialize)]
 struct Event {
     status: String,
+    // cancelled instances of recurring events come without summary and times
+    #[serde(default)]
     summary: String,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+    // missing for events without guests, e.g. personal blocks
+    #[serde(default)]
+    attendees: Vec<Attendee>,
+}
+
+#[derive(Deserialize)]
+struct Attendee {
+    // the calendar owner, emails can't be used as the calendar may be shared
+    #[serde(rename = "self", default)]
+    is_self: bool,
+    // needsAction, declined, tentative or accepted
+    #[serde(rename = "responseStatus", default)]
+    response_status: String,
+}
+
+impl Event {
+    fn is_declined(&self) -> bool {
+        self.attendees
+            .iter()
+            .any(|a| a.is_self && a.response_status == "declined")
+    }
 }
 
 // Work with Google Calendar API
 
-pub struct Calendar<'a> {
+// google redirects here with the code after authorization
+pub const OAUTH_PORT: u16 = 7890;
+
+pub const INSUFFICIENT_SCOPE: &str =
+    "Google token doesn't grant access to the calendar, re-authorization is required";
+
+pub struct Calendar {
     client: oauth2::basic::BasicClient,
-    config: &'a Config,
+    // replaced when google rejects the access token before it expires
+    token: RefCell<Option<GoogleToken>>,
+    refreshed: Cell<bool>,
 }
 
-impl Calendar<'_> {
+impl Calendar {
     pub fn new(cfg: &Config) -> Calendar {
         let auth_url =
             AuthUrl::new(Url::parse("https://accounts.google.com/o/oauth2/v2/auth").unwrap());
@@ -62,12 +134,13 @@ impl Calendar<'_> {
             Some(token_url),
         )
         .set_redirect_url(RedirectUrl::new(
-            Url::parse("http://localhost:7890").unwrap(),
+            Url::parse(&format!("http://localhost:{}", OAUTH_PORT)).unwrap(),
         ));
 
         Calendar {
             client,
-            config: cfg,
+            token: RefCell::new(cfg.google_token.clone()),
+            refreshed: Cell::new(false),
         }
     }
 
@@ -82,13 +155,16 @@ impl Calendar<'_> {
                 "https://www.googleapis.com/auth/calendar.events.readonly".to_string(),
             ))
             .set_response_type(&ResponseType::new("code".to_string()))
+            // without consent google doesn't return refresh token when re-authorizing
+            .add_extra_param("prompt", "consent")
             .url();
         String::from(url.as_str())
     }
 
     // the server would panic if anything goes wrong, not sure if I really need to fix it
     pub fn listen_for_code(&self) -> GoogleToken {
-        let listener = TcpListener::bind("127.0.0.1:7890").expect("can not open 7890 port");
+        let listener = TcpListener::bind(("127.0.0.1", OAUTH_PORT))
+            .unwrap_or_else(|_| panic!("can not open {} port", OAUTH_PORT));
         for stream in listener.incoming() {
             match stream {
                 Ok(mut stream) => {
@@ -102,7 +178,7 @@ impl Calendar<'_> {
                     let code_pair = url
                         .query_pairs()
                         .find(|pair| {
-                            let &(ref key, _) = pair;
+                            let (key, _) = pair;
                             key == "code"
                         })
                         .unwrap();
@@ -121,7 +197,7 @@ impl Calendar<'_> {
                     let token = &self
                         .client
                         .exchange_code(code)
-                        .request(http_client)
+                        .request(http::oauth_client)
                         .expect("can't get access token");
                     return Self::config_from_token(token);
                 }
@@ -156,38 +232,53 @@ impl Calendar<'_> {
         }
     }
 
-    fn access_token(&self) -> Result<String, String> {
-        match &self.config.google_token {
+    // token refreshed during requests that should be saved into the config
+    pub fn refreshed_token(&self) -> Option<GoogleToken> {
+        if self.refreshed.get() {
+            self.token.borrow().clone()
+        } else {
+            None
+        }
+    }
+
+    fn access_token(&self) -> Result<String, StandupError> {
+        match &*self.token.borrow() {
             Some(s) => Ok(s.access_token.clone()),
-            None => Err(String::from("no token config")),
+            None => Err(StandupError::Auth(String::from("no token config"))),
         }
     }
 
-    pub fn refresh_if_needed(&self) -> Result<Option<GoogleToken>, String> {
-        let experies_at = match &self.config.google_token {
+    pub fn refresh_if_needed(&self) -> Result<(), StandupError> {
+        let experies_at = match &*self.token.borrow() {
             Some(s) => s.experies_at,
-            None => return Err(String::from("no token config")),
+            None => return Err(StandupError::Auth(String::from("no token config"))),
         };
 
         // FIXME need some buffer here
         if experies_at < Utc::now() {
-            Ok(Some(self.refresh_token()?))
-        } else {
-            Ok(None)
+            info!("Google access token expired at {}, refreshing", experies_at);
+            self.replace_token(self.refresh_token()?);
         }
+        Ok(())
+    }
+
+    fn replace_token(&self, token: GoogleToken) {
+        self.token.replace(Some(token));
+        self.refreshed.set(true);
     }
 
-    fn refresh_token(&self) -> Result<GoogleToken, String> {
-        let saved_token = match &self.config.google_token {
+    fn refresh_token(&self) -> Result<GoogleToken, StandupError> {
+        let saved_token = self.token.borrow();
+        let saved_token = match &*saved_token {
             Some(s) => s,
-            None => return Err(String::from("no token in config")),
+            None => return Err(StandupError::Auth(String::from("no token in config"))),
         };
 
         let token = self
             .client
             .exchange_refresh_token(&RefreshToken::new(saved_token.refresh_token.clone()))
-            .request(http_client)
-            .map_err(|e| format!("Can't refresh token: {}", e))?;
+            .request(http::oauth_client)
+            .map_err(|e| StandupError::Auth(format!("Can't refresh token: {}", e)))?;
 
         let access_token = String::from(token.access_token().secret());
         let experies_at = Utc::now()
@@ -205,58 +296,331 @@ impl Calendar<'_> {
         })
     }
 
-    pub fn list(&self) -> Result<Vec<ListItem>, String> {
-        let mut resp = reqwest::Client::new()
-            .get(&format!(
-                "https://www.googleapis.com/calendar/v3/users/me/calendarList?access_token={}",
-                self.access_token()?,
-            ))
-            .send()
-            .map_err(|e| format!("Request to Google Calendar failed: {}", e))?
-            .error_for_status()
-            .map_err(|e| format!("Incorrect response status: {}", e))?;
+    // access token is added to the url here, never log the full url
+    fn request(&self, url: &str) -> Result<reqwest::Response, StandupError> {
+        let send = |token: String| -> Result<reqwest::Response, StandupError> {
+            Ok(http::client()?
+                .get(&format!("{}&access_token={}", url, token))
+                .send()?)
+        };
+
+        let mut resp = send(self.access_token()?)?;
+        // the token can be revoked before it expires, it is refreshed and retried once
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            info!("Google rejected the access token, refreshing");
+            self.replace_token(self.refresh_token()?);
+            resp = send(self.access_token()?)?;
+        }
+
+        // token issued by an older version doesn't have all the scopes authorize_url asks for
+        if resp.status() == StatusCode::FORBIDDEN {
+            let body = resp.text().unwrap_or_default();
+            if body.contains("insufficientPermissions")
+                || body.contains("ACCESS_TOKEN_SCOPE_INSUFFICIENT")
+            {
+                return Err(StandupError::Auth(String::from(INSUFFICIENT_SCOPE)));
+            }
+        }
 
-        let json: ListResp = resp
-            .json()
-            .map_err(|e| format!("Can not parse Google Calendar response: {}", e))?;
+        Ok(resp.error_for_status()?)
+    }
+
+    pub fn list(&self) -> Result<Vec<ListItem>, StandupError> {
+        debug!("GET calendar list");
+        let mut resp =
+            self.request("https://www.googleapis.com/calendar/v3/users/me/calendarList?")?;
+
+        let json: ListResp = serde_json::from_str(&resp.text()?)?;
 
         Ok(json.items)
     }
 
     pub fn events(
         &self,
+        calendar_id: &str,
         since: DateTime<Utc>,
-        until: Option<DateTime<Utc>>,
-    ) -> Result<Vec<Entry>, String> {
-        let mut resp = reqwest::Client::new()
-            .get(&format!(
-                "https://www.googleapis.com/calendar/v3/calendars/{}/events?singleEvents=true&timeMin={}&timeMax={}&access_token={}",
-                self.config.gcal.as_ref().unwrap().id,
-                since.to_rfc3339_opts(SecondsFormat::Secs, true),
-                until.unwrap_or_else(Utc::now).to_rfc3339_opts(SecondsFormat::Secs, true),
-                self.access_token()?,
-            ))
-            .send()
-            .map_err(|e| format!("Request to Google Calendar failed: {}", e))?
-            .error_for_status()
-            .map_err(|e| format!("Incorrect response status: {}", e))?;
+        until: DateTime<Utc>,
+        include_declined: bool,
+    ) -> Result<Vec<Entry>, StandupError> {
+        debug!("GET events of calendar {} since {}", calendar_id, since);
+        let mut resp = self.request(events_url(calendar_id, since, until).as_str())?;
 
-        let json: EventsResp = resp
-            .json()
-            .map_err(|e| format!("Can not parse Google Calendar response: {}", e))?;
+        let json: EventsResp = serde_json::from_str(&resp.text()?)?;
+        debug!("received {} calendar events", json.items.len());
 
-        let events: Vec<_> = json
-            .items
-            .iter()
-            .filter(|x| x.status == "confirmed")
-            .map(|x| Entry {
+        Ok(filter_events(json.items, since, until, include_declined))
+    }
+}
+
+// ids of shared calendars contain # and @, e.g. en.usa#holiday@group.v.calendar.google.com
+fn events_url(calendar_id: &str, since: DateTime<Utc>, until: DateTime<Utc>) -> Url {
+    let mut url = Url::parse("https://www.googleapis.com/calendar/v3/calendars").unwrap();
+    url.path_segments_mut()
+        .unwrap()
+        .push(calendar_id)
+        .push("events");
+    url.query_pairs_mut()
+        .append_pair("singleEvents", "true")
+        .append_pair("timeMin", &since.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .append_pair("timeMax", &until.to_rfc3339_opts(SecondsFormat::Secs, true));
+    url
+}
+
+// Transformations
+
+// meetings that overlap the window at all are kept, e.g. an all-hands that started
+// before since and ran past it. The same meeting can come several times
+// as overlapping recurring instances, only the first one is kept.
+// The API doesn't order single events, meetings are sorted by start
+fn filter_events(
+    mut events: Vec<Event>,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    include_declined: bool,
+) -> Vec<Entry> {
+    events.sort_by_key(|x| x.start.as_ref().and_then(EventTime::instant));
+    let mut seen = HashSet::new();
+    events
+        .into_iter()
+        .filter(|x| x.status == "confirmed")
+        .filter(|x| include_declined || !x.is_declined())
+        .filter(|x| {
+            let start = x.start.as_ref().and_then(EventTime::instant);
+            let end = x.end.as_ref().and_then(EventTime::instant);
+            start.is_none_or(|d| d < until) && end.is_none_or(|d| d > since)
+        })
+        .filter(|x| {
+            let start = x.start.as_ref().and_then(EventTime::instant);
+            seen.insert((x.summary.clone(), start))
+        })
+        .map(|x| {
+            let title = match time_range(x.start.as_ref(), x.end.as_ref()) {
+                Some(time) => format!("{} ({})", x.summary, time),
+                None => x.summary,
+            };
+            Entry {
                 r#type: String::from("Meeting"),
-                title: x.summary.clone(),
-                url: None,
-                actions: Vec::new(),
-            })
-            .collect();
+                title,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<Event> {
+        let json: EventsResp =
+            serde_json::from_str(include_str!("../tests/fixtures/gcalendar_events.json"))
+                .expect("fixture must be valid");
+        json.items
+    }
+
+    fn since() -> DateTime<Utc> {
+        Utc.ymd(2019, 8, 5).and_hms(22, 0, 0)
+    }
+
+    fn until() -> DateTime<Utc> {
+        Utc.ymd(2019, 8, 6).and_hms(22, 0, 0)
+    }
+
+    fn titles(entries: &[Entry]) -> Vec<&str> {
+        // without the time of the meeting
+        entries
+            .iter()
+            .map(|e| e.title.split(" (").next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_only_confirmed_events() {
+        let entries = filter_events(fixture(), since(), until(), false);
+
+        // all-day events start at local midnight, so their position depends on the timezone
+        let mut titles = titles(&entries);
+        titles.sort_unstable();
+        assert_eq!(
+            titles,
+            vec!["Company Offsite", "Engineering Demo", "Standup"]
+        );
+    }
+
+    #[test]
+    fn skips_declined_unless_included() {
+        let entries = filter_events(fixture(), since(), until(), true);
+
+        let mut titles = titles(&entries);
+        titles.sort_unstable();
+        assert_eq!(
+            titles,
+            vec![
+                "Company Offsite",
+                "Engineering Demo",
+                "Sales Sync",
+                "Standup"
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_to_meeting_entries() {
+        let entries = filter_events(fixture(), since(), until(), false);
+
+        for e in &entries {
+            assert_eq!(e.r#type, "Meeting");
+            assert!(e.url.is_none());
+            assert!(e.actions.is_empty());
+        }
+    }
+
+    #[test]
+    fn encodes_calendar_id() {
+        let url = events_url(
+            "en.usa#holiday@group.v.calendar.google.com",
+            since(),
+            until(),
+        );
+        assert_eq!(
+            url.as_str(),
+            "https://www.googleapis.com/calendar/v3/calendars/en.usa%23holiday@group.v.calendar.google.com/events\
+             ?singleEvents=true&timeMin=2019-08-05T22%3A00%3A00Z&timeMax=2019-08-06T22%3A00%3A00Z"
+        );
+    }
+
+    #[test]
+    fn empty_calendar() {
+        assert!(filter_events(Vec::new(), since(), until(), false).is_empty());
+    }
+
+    fn event(summary: &str, start: &str, end: &str) -> Event {
+        serde_json::from_value(serde_json::json!({
+            "status": "confirmed",
+            "summary": summary,
+            "start": { "dateTime": start },
+            "end": { "dateTime": end },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn keeps_meetings_overlapping_window() {
+        let events = vec![
+            event("Before", "2019-08-05T20:00:00Z", "2019-08-05T22:00:00Z"),
+            event("All Hands", "2019-08-05T21:00:00Z", "2019-08-05T23:00:00Z"),
+            event("Late Call", "2019-08-06T21:30:00Z", "2019-08-06T23:00:00Z"),
+            event("After", "2019-08-06T22:00:00Z", "2019-08-06T23:00:00Z"),
+        ];
+
+        let entries = filter_events(events, since(), until(), false);
+        assert_eq!(titles(&entries), vec!["All Hands", "Late Call"]);
+    }
+
+    #[test]
+    fn shows_meeting_times() {
+        let at = |hour, min| Local.ymd(2019, 8, 6).and_hms(hour, min, 0).to_rfc3339();
+        let all_day: Event = serde_json::from_value(serde_json::json!({
+            "status": "confirmed",
+            "summary": "Offsite",
+            "start": { "date": "2019-08-06" },
+            "end": { "date": "2019-08-07" },
+        }))
+        .unwrap();
+        let no_end: Event = serde_json::from_value(serde_json::json!({
+            "status": "confirmed",
+            "summary": "Call",
+            "start": { "dateTime": at(11, 0) },
+        }))
+        .unwrap();
+        let no_times: Event = serde_json::from_value(serde_json::json!({
+            "status": "confirmed",
+            "summary": "Someday",
+        }))
+        .unwrap();
+        let events = vec![
+            event("Standup", &at(9, 0), &at(9, 30)),
+            all_day,
+            no_end,
+            no_times,
+        ];
+
+        let since = Local.ymd(2019, 8, 6).and_hms(0, 0, 0).with_timezone(&Utc);
+        let mut entries: Vec<String> =
+            filter_events(events, since, since + Duration::days(1), false)
+                .into_iter()
+                .map(|e| e.title)
+                .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                "Call (11:00)",
+                "Offsite (all day)",
+                "Someday",
+                "Standup (09:00–09:30)"
+            ]
+        );
+    }
+
+    #[test]
+    fn declined_by_self() {
+        let with_attendees = |attendees| -> Event {
+            serde_json::from_value(serde_json::json!({
+                "status": "confirmed",
+                "summary": "Planning",
+                "attendees": attendees,
+            }))
+            .unwrap()
+        };
+        let declined = with_attendees(serde_json::json!([
+            { "email": "me@example.com", "self": true, "responseStatus": "declined" },
+            { "email": "bob@example.com", "responseStatus": "accepted" },
+        ]));
+        let declined_by_other = with_attendees(serde_json::json!([
+            { "email": "me@example.com", "self": true, "responseStatus": "tentative" },
+            { "email": "bob@example.com", "responseStatus": "declined" },
+        ]));
+
+        assert!(declined.is_declined());
+        assert!(!declined_by_other.is_declined());
+        assert!(!event("Focus", "2019-08-06T07:00:00Z", "2019-08-06T09:00:00Z").is_declined());
+    }
+
+    #[test]
+    fn sorts_meetings_by_start() {
+        let events = vec![
+            event(
+                "Demo",
+                "2019-08-06T16:00:00+02:00",
+                "2019-08-06T17:00:00+02:00",
+            ),
+            event(
+                "Standup",
+                "2019-08-06T09:00:00+02:00",
+                "2019-08-06T09:15:00+02:00",
+            ),
+            event("Sync", "2019-08-06T08:30:00Z", "2019-08-06T09:00:00Z"),
+        ];
+
+        let entries = filter_events(events, since(), until(), false);
+        assert_eq!(titles(&entries), vec!["Standup", "Sync", "Demo"]);
+    }
 
-        Ok(events)
+    #[test]
+    fn dedups_by_summary_and_start() {
+        let events = vec![
+            event("Standup", "2019-08-06T07:00:00Z", "2019-08-06T07:15:00Z"),
+            event(
+                "Standup",
+                "2019-08-06T07:00:00+00:00",
+                "2019-08-06T07:30:00Z",
+            ),
+            event("Standup", "2019-08-06T15:00:00Z", "2019-08-06T15:15:00Z"),
+            event("Sync", "2019-08-06T07:00:00Z", "2019-08-06T07:15:00Z"),
+        ];
+
+        let entries = filter_events(events, since(), until(), false);
+        assert_eq!(titles(&entries), vec!["Standup", "Sync", "Standup"]);
     }
 }
@@ -1,10 +1,24 @@
 // TODO: figure how to handle prs updates (push)
 
 use chrono::prelude::*;
-use reqwest::header::{HeaderMap, AUTHORIZATION, LINK};
-use serde::Deserialize;
+use log::{debug, info, warn};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LINK,
+};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use time::Duration;
+use url::Url;
+
+use crate::cache::{Cache, CachedResponse};
+use crate::error::StandupError;
+use crate::http;
 use crate::report::*;
 
 // Github response structs
@@ -13,6 +27,8 @@ use crate::report::*;
 struct Repo {
     full_name: String,
     source: Option<Box<Repo>>,
+    #[serde(default)]
+    archived: bool,
 }
 
 #[derive(Deserialize)]
@@ -20,19 +36,64 @@ struct EventRepo {
     name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 struct User {
     login: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Clone, Deserialize)]
+struct Branch {
+    r#ref: String,
+}
+
+#[derive(Clone, Deserialize)]
 struct PullRequest {
     number: u64,
     html_url: String,
     title: String,
     #[serde(default)]
     merged: bool,
+    merged_at: Option<DateTime<Utc>>,
+    merged_by: Option<User>,
+    merge_commit_sha: Option<String>,
     user: User,
+    #[serde(default)]
+    labels: Vec<Label>,
+    body: Option<String>,
+    base: Option<Branch>,
+    // missing in simplified objects, e.g. in review events
+    comments: Option<u64>,
+    review_comments: Option<u64>,
+}
+
+impl PullRequest {
+    // conversation and review comments together
+    fn comment_count(&self) -> Option<u64> {
+        self.comments
+            .map(|c| c + self.review_comments.unwrap_or_default())
+    }
+
+    fn entry(&self, actions: Vec<String>) -> Entry {
+        Entry {
+            r#type: String::from("PR"),
+            number: Some(self.number),
+            title: self.title.clone(),
+            url: Some(self.html_url.clone()),
+            actions,
+            labels: self.labels.iter().map(|l| l.name.clone()).collect(),
+            body: self.body.as_deref().and_then(excerpt),
+            base: self.base.as_ref().map(|b| b.r#ref.clone()),
+            author: Some(self.user.login.clone()),
+            comments: self.comment_count(),
+            closes: self.body.as_deref().map(closed_issues).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -47,6 +108,58 @@ struct Issue {
     html_url: String,
     title: String,
     user: User,
+    #[serde(default)]
+    labels: Vec<Label>,
+    body: Option<String>,
+    comments: Option<u64>,
+}
+
+impl Issue {
+    // issues api returns pull requests as well, so the type is chosen by the caller
+    fn entry(&self, r#type: &str, actions: Vec<String>) -> Entry {
+        Entry {
+            r#type: String::from(r#type),
+            number: Some(self.number),
+            title: self.title.clone(),
+            url: Some(self.html_url.clone()),
+            actions,
+            labels: self.labels.iter().map(|l| l.name.clone()).collect(),
+            body: self.body.as_deref().and_then(excerpt),
+            author: Some(self.user.login.clone()),
+            comments: self.comments,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Review {
+    user: Option<User>,
+    state: String,
+}
+
+// only the latest decision of every reviewer counts,
+// comments don't change a previous approval or change request
+fn count_reviews(reviews: &[Review]) -> ReviewCounts {
+    let mut decisions: HashMap<&str, &str> = HashMap::new();
+    for r in reviews {
+        let login = match &r.user {
+            Some(u) => u.login.as_str(),
+            None => continue,
+        };
+        match r.state.as_str() {
+            "APPROVED" | "CHANGES_REQUESTED" | "DISMISSED" => {
+                decisions.insert(login, r.state.as_str());
+            }
+            _ => (),
+        }
+    }
+
+    let count = |state| decisions.values().filter(|s| **s == state).count() as u32;
+    ReviewCounts {
+        approvals: count("APPROVED"),
+        changes_requested: count("CHANGES_REQUESTED"),
+    }
 }
 
 #[derive(Deserialize)]
@@ -76,10 +189,44 @@ struct IssueCommentPayload {
 #[derive(Deserialize)]
 struct PushPayload {
     r#ref: String,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
     #[serde(skip)]
     pull_requests: Option<Vec<PullRequest>>,
 }
 
+#[derive(Deserialize)]
+struct PushCommit {
+    sha: String,
+    message: String,
+}
+
+impl PushPayload {
+    // events don't include parents of commits, so merges are recognized
+    // by the default messages git and github generate for them
+    fn is_merge_only(&self) -> bool {
+        !self.commits.is_empty()
+            && self.commits.iter().all(|c| {
+                c.message.starts_with("Merge branch ")
+                    || c.message.starts_with("Merge remote-tracking branch ")
+                    || c.message.starts_with("Merge pull request ")
+            })
+    }
+}
+
+#[derive(Deserialize)]
+struct GollumPayload {
+    pages: Vec<WikiPage>,
+}
+
+#[derive(Deserialize)]
+struct WikiPage {
+    title: String,
+    // created or edited
+    action: String,
+    html_url: String,
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "type", content = "payload")]
 enum EventPayload {
@@ -95,18 +242,353 @@ enum EventPayload {
     IssueComment(IssueCommentPayload),
     #[serde(rename = "PushEvent")]
     Push(PushPayload),
+    #[serde(rename = "GollumEvent")]
+    Gollum(GollumPayload),
+}
+
+// values of the type tag in EventPayload
+const EVENT_TYPES: &[&str] = &[
+    "PullRequestEvent",
+    "PullRequestReviewEvent",
+    "PullRequestReviewCommentEvent",
+    "IssuesEvent",
+    "IssueCommentEvent",
+    "PushEvent",
+    "GollumEvent",
+];
+
+impl EventPayload {
+    // numbers of pull requests and issues the event is about
+    fn numbers(&self) -> Vec<u64> {
+        match self {
+            EventPayload::PullRequest(p) => vec![p.pull_request.number],
+            EventPayload::Review(p) => vec![p.pull_request.number],
+            EventPayload::ReviewComment(p) => vec![p.pull_request.number],
+            EventPayload::Issue(p) => vec![p.issue.number],
+            EventPayload::IssueComment(p) => vec![p.issue.number],
+            EventPayload::Gollum(_) => Vec::new(),
+            EventPayload::Push(p) => p
+                .pull_requests
+                .iter()
+                .flatten()
+                .map(|pr| pr.number)
+                .collect(),
+        }
+    }
+
+    // comment counts of pull requests and issues at the time of the event
+    fn comment_counts(&self) -> Vec<(u64, u64)> {
+        let counts = match self {
+            EventPayload::PullRequest(p) => {
+                vec![(p.pull_request.number, p.pull_request.comment_count())]
+            }
+            EventPayload::Review(p) => {
+                vec![(p.pull_request.number, p.pull_request.comment_count())]
+            }
+            EventPayload::ReviewComment(p) => {
+                vec![(p.pull_request.number, p.pull_request.comment_count())]
+            }
+            EventPayload::Issue(p) => vec![(p.issue.number, p.issue.comments)],
+            EventPayload::IssueComment(p) => vec![(p.issue.number, p.issue.comments)],
+            EventPayload::Gollum(_) => Vec::new(),
+            EventPayload::Push(p) => p
+                .pull_requests
+                .iter()
+                .flatten()
+                .map(|pr| (pr.number, pr.comment_count()))
+                .collect(),
+        };
+        counts
+            .into_iter()
+            .filter_map(|(number, count)| count.map(|c| (number, c)))
+            .collect()
+    }
 }
 
 #[derive(Deserialize)]
 struct Event {
+    id: String,
     repo: EventRepo,
     #[serde(flatten)]
     payload: Option<EventPayload>,
     created_at: DateTime<Utc>,
 }
 
+#[derive(Deserialize)]
+struct PullStats {
+    additions: u64,
+    deletions: u64,
+}
+
+#[derive(Deserialize)]
+struct SearchResp {
+    items: Vec<SearchItem>,
+}
+
+#[derive(Deserialize)]
+struct SearchItem {
+    #[serde(flatten)]
+    issue: Issue,
+    // present only for pull requests
+    pull_request: Option<serde_json::Value>,
+}
+
+// Github GraphQL response structs
+
+#[derive(Deserialize)]
+struct GraphQLResp<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct ProjectsData {
+    viewer: ProjectsViewer,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectsViewer {
+    projects_v2: Nodes<Project>,
+}
+
+#[derive(Deserialize)]
+struct Project {
+    title: String,
+    items: Nodes<ProjectItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectItem {
+    updated_at: DateTime<Utc>,
+    content: Option<ProjectItemContent>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "__typename")]
+enum ProjectItemContent {
+    DraftIssue {
+        title: String,
+    },
+    Issue {
+        title: String,
+        url: String,
+        number: u64,
+    },
+    PullRequest {
+        title: String,
+        url: String,
+        number: u64,
+    },
+}
+
+// user projects only, organization projects require a query per organization
+const PROJECTS_QUERY: &str = "
+query {
+  viewer {
+    projectsV2(first: 20) {
+      nodes {
+        title
+        items(first: 100) {
+          nodes {
+            updatedAt
+            content {
+              __typename
+              ... on DraftIssue { title }
+              ... on Issue { title url number }
+              ... on PullRequest { title url number }
+            }
+          }
+        }
+      }
+    }
+  }
+}";
+
+#[derive(Deserialize)]
+struct DiscussionsData {
+    authored: Nodes<Discussion>,
+    commented: Nodes<Discussion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Discussion {
+    title: String,
+    url: String,
+    number: u64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    repository: DiscussionRepo,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscussionRepo {
+    name_with_owner: String,
+}
+
+// discussions aren't in the events feed, search knows only when they were updated last
+const DISCUSSIONS_QUERY: &str = "
+query($authored: String!, $commented: String!) {
+  authored: search(type: DISCUSSION, query: $authored, first: 50) {
+    nodes { ...discussion }
+  }
+  commented: search(type: DISCUSSION, query: $commented, first: 50) {
+    nodes { ...discussion }
+  }
+}
+
+fragment discussion on Discussion {
+  title
+  url
+  number
+  createdAt
+  updatedAt
+  repository { nameWithOwner }
+}";
+
 // helpers
 
+// body and headers of a successful response, the body may come from the cache
+struct ApiResponse {
+    url: String,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl ApiResponse {
+    fn read(mut resp: reqwest::Response) -> Result<ApiResponse, StandupError> {
+        let body = resp.text()?;
+        Ok(ApiResponse {
+            url: resp.url().to_string(),
+            headers: resp.headers().clone(),
+            body,
+        })
+    }
+
+    // saved headers with fresh ones of the 304 on top, e.g. rate limits
+    fn from_cache(cached: CachedResponse, fresh: &HeaderMap) -> ApiResponse {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &cached.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        for (name, value) in fresh {
+            headers.insert(name.clone(), value.clone());
+        }
+        ApiResponse {
+            url: cached.url,
+            headers,
+            body: cached.body,
+        }
+    }
+}
+
+// maintenance and rate limit pages come as html, sometimes with 200 status
+fn parse_json<T: DeserializeOwned>(resp: &ApiResponse) -> Result<T, StandupError> {
+    let content_type = resp
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    if !content_type.contains("json") {
+        let snippet: String = resp.body.chars().take(200).collect();
+        return Err(StandupError::Api(format!(
+            "GitHub returned an unexpected response ({}) from {}: {}",
+            content_type,
+            resp.url,
+            snippet.trim(),
+        )));
+    }
+
+    Ok(serde_json::from_str(&resp.body)?)
+}
+
+// 401 means the token expired or was revoked, other errors are kept as is
+fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, StandupError> {
+    if resp.status() == StatusCode::UNAUTHORIZED {
+        return Err(StandupError::Auth(String::from(
+            "GitHub rejected the token, it may be expired or revoked",
+        )));
+    }
+    Ok(resp.error_for_status()?)
+}
+
+const EXCERPT_WIDTH: usize = 80;
+
+// first meaningful line of a markdown body
+fn excerpt(body: &str) -> Option<String> {
+    body.lines()
+        .map(|x| x.trim())
+        .find(|x| !x.is_empty() && !x.starts_with("<!--"))
+        .map(|x| truncate(x, EXCERPT_WIDTH))
+}
+
+// https://docs.github.com/en/issues/tracking-your-work-with-issues/linking-a-pull-request-to-an-issue
+const CLOSING_KEYWORDS: &[&str] = &[
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+// numbers of issues of the same repository referenced as "Fixes #123",
+// the keyword may be followed by a colon
+fn closed_issues(body: &str) -> Vec<u64> {
+    let words: Vec<&str> = body.split_whitespace().collect();
+    let mut res = Vec::new();
+    for pair in words.windows(2) {
+        let keyword = pair[0].trim_end_matches(':').to_lowercase();
+        if !CLOSING_KEYWORDS.contains(&keyword.as_str()) {
+            continue;
+        }
+        let number = pair[1]
+            .strip_prefix('#')
+            .map(|v| v.trim_end_matches(|c: char| !c.is_ascii_digit()))
+            .and_then(|v| v.parse::<u64>().ok());
+        if let Some(n) = number.filter(|n| !res.contains(n)) {
+            res.push(n);
+        }
+    }
+    res
+}
+
+// shell-like pattern where * matches any sequence and ? any single character
+fn glob_match(pattern: &str, v: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let v: Vec<char> = v.chars().collect();
+    // matches[j] is true when pattern[..i] matches v[..j]
+    let mut matches = vec![false; v.len() + 1];
+    matches[0] = true;
+    for p in pattern {
+        let mut next = vec![false; v.len() + 1];
+        for j in 0..=v.len() {
+            next[j] = match p {
+                '*' => matches[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matches[j - 1],
+                c => j > 0 && matches[j - 1] && v[j - 1] == c,
+            };
+        }
+        matches = next;
+    }
+    matches[v.len()]
+}
+
 // typed link header isn't implemented in headers 0.2.1
 struct LinkHeader {
     next: Option<String>,
@@ -128,9 +610,54 @@ impl LinkHeader {
     }
 }
 
+// Progress of events pagination saved after every page,
+// allows to resume interrupted fetch with the same parameters
+#[derive(Serialize, Deserialize)]
+struct Progress {
+    user: String,
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+    next_page: u8,
+    events: Vec<serde_json::Value>,
+}
+
+impl Progress {
+    fn load(file_path: &Path) -> Result<Option<Progress>, StandupError> {
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(file_path)
+            .map_err(|e| StandupError::io("can not read progress file", e))?;
+        let progress = serde_json::from_str(&json)?;
+
+        Ok(Some(progress))
+    }
+
+    fn save(&self, file_path: &Path) -> Result<(), StandupError> {
+        let json = serde_json::to_string(&self)?;
+        fs::write(file_path, json).map_err(|e| StandupError::io("can not write progress file", e))
+    }
+
+    fn clear(file_path: &Path) -> Result<(), StandupError> {
+        if !file_path.exists() {
+            return Ok(());
+        }
+        fs::remove_file(file_path).map_err(|e| StandupError::io("can not remove progress file", e))
+    }
+}
+
 struct GithubApi<'a> {
     user: &'a str,
     token: &'a str,
+    // tried for repositories the main token can't access, e.g. SSO-gated orgs
+    extra_tokens: &'a [String],
+    // of GETs failing with 5xx or a rate limit, see retry_delay
+    max_retries: u32,
+    // None with --no-cache
+    cache: Option<Cache>,
+    // without the trailing slash, see api_url()
+    api_url: &'a str,
 }
 
 impl GithubApi<'_> {
@@ -138,96 +665,378 @@ impl GithubApi<'_> {
         &self,
         since: DateTime<Utc>,
         until: Option<DateTime<Utc>>,
-    ) -> Result<Vec<Event>, String> {
-        let mut events = Vec::new();
+        opts: &Options,
+    ) -> Result<Vec<Event>, StandupError> {
+        let progress_file = opts.progress_file.as_path();
+        let mut progress = Progress {
+            user: String::from(self.user),
+            since,
+            until,
+            next_page: 1,
+            events: Vec::new(),
+        };
+        if opts.resume {
+            match Progress::load(progress_file)? {
+                Some(p) if p.user == progress.user && p.since == since && p.until == until => {
+                    info!("resuming events fetch from page {}", p.next_page);
+                    progress = p;
+                }
+                Some(_) => warn!("Saved progress is for a different request, starting over"),
+                None => warn!("No saved progress found, starting over"),
+            }
+        }
+
+        let mut seen: HashSet<String> = progress
+            .events
+            .iter()
+            .filter_map(|x| x["id"].as_str().map(String::from))
+            .collect();
         let mut stop = false;
-        let mut page: u8 = 1;
         // call github until event with created_at <= since is found
         // or no more events available
         loop {
-            let (page_events, has_next_page) = self.events_page_request(page)?;
-            if !has_next_page && !page_events.is_empty() {
-                let last_event = &page_events[page_events.len() - 1];
-                if last_event.created_at > since {
-                    println!(
-                        "WARNING: Events since requested date are unavailable. Last event date: {}",
-                        last_event.created_at,
-                    );
+            let page = progress.next_page;
+            let (page_events, has_next_page, rate_limit) = self.events_page_request(page)?;
+            debug!(
+                "fetched events page {} with {} events",
+                page,
+                page_events.len()
+            );
+
+            let mut last_created_at = None;
+            for raw in page_events {
+                let event: Event = serde_json::from_value(raw.clone())?;
+                last_created_at = Some(event.created_at);
+
+                // payload of a known event that doesn't match the structs is silently dropped
+                if event.payload.is_none() {
+                    let r#type = raw["type"].as_str().unwrap_or_default();
+                    if EVENT_TYPES.contains(&r#type) {
+                        let msg = format!("Can not parse {} {}, skipping it", r#type, event.id);
+                        if opts.strict {
+                            return Err(StandupError::Api(msg));
+                        }
+                        warn!("{}", msg);
+                    }
+                }
+
+                if event.created_at < since {
+                    stop = true;
+                    continue;
+                }
+                if until.is_some_and(|d| event.created_at >= d) || event.payload.is_none() {
+                    continue;
+                }
+                // pages shift when new events arrive, resumed fetch may see an event twice
+                if seen.insert(event.id) {
+                    progress.events.push(raw);
+                }
+                if opts.last.is_some_and(|n| progress.events.len() >= n) {
+                    stop = true;
+                    break;
                 }
             }
 
-            let events_iter = page_events
-                .into_iter()
-                .filter(|x| {
-                    if x.created_at >= since {
-                        true
-                    } else {
-                        stop = true;
-                        false
+            if let Some(created_at) = last_created_at {
+                if !has_next_page && created_at > since && opts.last.is_none() {
+                    let msg = format!(
+                        "Events since requested date are unavailable. Last event date: {}",
+                        created_at,
+                    );
+                    if opts.strict {
+                        return Err(StandupError::Api(msg));
                     }
-                })
-                .filter(|x| until.map_or(true, |d| x.created_at < d))
-                .filter(|x| x.payload.is_some());
-
-            events.extend(events_iter);
+                    warn!("{}", msg);
+                }
+            }
 
             if stop || !has_next_page {
                 break;
             }
 
-            page += 1;
+            progress.next_page += 1;
+            progress.save(progress_file)?;
+
+            if let Some(r) = rate_limit {
+                if let Some(delay) = r.delay(Utc::now()) {
+                    info!(
+                        "{} GitHub requests left until {}, waiting {:?} before the next page",
+                        r.remaining, r.reset, delay
+                    );
+                    thread::sleep(delay);
+                }
+            }
         }
 
-        Ok(events)
+        Progress::clear(progress_file)?;
+
+        progress
+            .events
+            .into_iter()
+            .map(|x| Ok(serde_json::from_value(x)?))
+            .collect()
+    }
+
+    // returns None if the repository was deleted, transferred or made private
+    fn get_repo(&self, repo: &str) -> Result<Option<Repo>, StandupError> {
+        let resp = match self.request_with_any_token(&format!("{}/repos/{}", self.api_url, repo))? {
+            Some(resp) => resp,
+            None => return Ok(None),
+        };
+
+        let repo: Repo = parse_json(&resp)?;
+
+        Ok(Some(repo))
     }
 
-    fn get_repo(&self, repo: &str) -> Result<Repo, String> {
-        let mut resp = self.request(&format!("https://api.github.com/repos/{}", repo,))?;
+    // returns None if the repository doesn't exist or isn't accessible with the token
+    fn find_prs(&self, repo: &str, head: &str) -> Result<Option<Vec<PullRequest>>, StandupError> {
+        let resp = match self.request_with_any_token(&format!(
+            "{}/repos/{}/pulls?state=all&head={}",
+            self.api_url, repo, head,
+        ))? {
+            Some(resp) => resp,
+            None => return Ok(None),
+        };
+
+        let prs: Vec<PullRequest> = parse_json(&resp)?;
+
+        Ok(Some(prs))
+    }
 
-        let repo: Repo = resp
-            .json()
-            .map_err(|e| format!("Can not parse Github response: {}", e))?;
+    fn search_issues(&self, query: &str) -> Result<Vec<SearchItem>, StandupError> {
+        let url = Url::parse_with_params(
+            &format!("{}/search/issues", self.api_url),
+            &[("q", query), ("per_page", "100")],
+        )
+        .map_err(|e| StandupError::Invalid(format!("Can not build search url: {}", e)))?;
+        let resp = self.request(url.as_str())?;
 
-        Ok(repo)
+        let json: SearchResp = parse_json(&resp)?;
+        Ok(json.items)
     }
 
-    fn find_prs(&self, repo: &str, head: &str) -> Result<Vec<PullRequest>, String> {
-        let mut resp = self.request(&format!(
-            "https://api.github.com/repos/{}/pulls?state=all&head={}",
-            repo, head,
+    // lines added and deleted, events payloads don't always have them
+    fn pull_changes(&self, repo: &str, number: u64) -> Result<u64, StandupError> {
+        let resp = self.request(&format!("{}/repos/{}/pulls/{}", self.api_url, repo, number,))?;
+
+        let stats: PullStats = parse_json(&resp)?;
+        Ok(stats.additions + stats.deletions)
+    }
+
+    // target branch of a PR known only from comments, issue payloads don't include it
+    fn pull_base(&self, repo: &str, number: u64) -> Result<Option<String>, StandupError> {
+        let resp = self.request(&format!("{}/repos/{}/pulls/{}", self.api_url, repo, number))?;
+
+        let pr: PullRequest = parse_json(&resp)?;
+        Ok(pr.base.map(|b| b.r#ref))
+    }
+
+    fn reviews(&self, repo: &str, number: u64) -> Result<Vec<Review>, StandupError> {
+        let resp = self.request(&format!(
+            "{}/repos/{}/pulls/{}/reviews?per_page=100",
+            self.api_url, repo, number,
         ))?;
 
-        let prs: Vec<PullRequest> = resp
-            .json()
-            .map_err(|e| format!("Can not parse Github response: {}", e))?;
+        parse_json(&resp)
+    }
 
-        Ok(prs)
+    fn request(&self, url: &str) -> Result<ApiResponse, StandupError> {
+        self.request_if_found(url)?.ok_or_else(|| {
+            StandupError::Api(format!(
+                "Incorrect response status: 404 Not Found for {}",
+                url
+            ))
+        })
     }
 
-    fn request(&self, url: &str) -> Result<reqwest::Response, String> {
-        let resp = reqwest::Client::new()
-            .get(url)
-            .header(AUTHORIZATION, format!("token {}", self.token))
-            .send()
-            .map_err(|e| format!("Request to Github failed: {}", e))?
-            .error_for_status()
-            .map_err(|e| format!("Incorrect response status: {}", e))?;
+    // Github responds with 404 for private resources as well
+    fn request_if_found(&self, url: &str) -> Result<Option<ApiResponse>, StandupError> {
+        let (resp, cached) = self.send(url, self.token)?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(self.finish(url, resp, cached)?))
+    }
+
+    // same as request_if_found but falls back to extra tokens
+    // if the resource is hidden (404) or forbidden (403) for the main token
+    fn request_with_any_token(&self, url: &str) -> Result<Option<ApiResponse>, StandupError> {
+        let tokens =
+            std::iter::once(self.token).chain(self.extra_tokens.iter().map(String::as_str));
+        let mut last = None;
+        for (i, token) in tokens.enumerate() {
+            if i > 0 {
+                debug!("retrying with extra token #{}", i);
+            }
+            let (resp, cached) = self.send(url, token)?;
+            match resp.status() {
+                StatusCode::NOT_FOUND | StatusCode::FORBIDDEN => last = Some(resp),
+                _ => return self.finish(url, resp, cached).map(Some),
+            }
+        }
 
+        match last {
+            Some(resp) if resp.status() == StatusCode::FORBIDDEN => {
+                ApiResponse::read(check_status(resp)?).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // the saved entry is returned to be used if GitHub responds with 304
+    fn send(
+        &self,
+        url: &str,
+        token: &str,
+    ) -> Result<(reqwest::Response, Option<CachedResponse>), StandupError> {
+        let cached = self.cache.as_ref().and_then(|c| c.get(url));
+        let mut attempt = 0;
+        loop {
+            // the token is sent in a header, so the url is safe to log
+            debug!("GET {}", url);
+            let mut req = http::client()?
+                .get(url)
+                .header(AUTHORIZATION, format!("token {}", token));
+            if let Some(cached) = &cached {
+                req = req.header(IF_NONE_MATCH, cached.etag.as_str());
+            }
+            let resp = req.send()?;
+            if attempt >= self.max_retries {
+                return Ok((resp, cached));
+            }
+            let delay = match retry_delay(resp.status(), resp.headers(), attempt, Utc::now()) {
+                Some(delay) => delay,
+                None => return Ok((resp, cached)),
+            };
+            attempt += 1;
+            warn!(
+                "GitHub responded with {} to {}, retrying in {:?} ({}/{})",
+                resp.status(),
+                url,
+                delay,
+                attempt,
+                self.max_retries
+            );
+            thread::sleep(delay);
+        }
+    }
+
+    // the body of a 304 is taken from the cache, other responses with an ETag are saved
+    fn finish(
+        &self,
+        url: &str,
+        resp: reqwest::Response,
+        cached: Option<CachedResponse>,
+    ) -> Result<ApiResponse, StandupError> {
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (resp.status(), cached) {
+            debug!("{} is not modified, using the cached response", url);
+            return Ok(ApiResponse::from_cache(cached, resp.headers()));
+        }
+
+        let resp = ApiResponse::read(check_status(resp)?)?;
+        let etag = resp.headers.get(ETAG).and_then(|v| v.to_str().ok());
+        if let (Some(cache), Some(etag)) = (&self.cache, etag) {
+            let entry = CachedResponse {
+                url: String::from(url),
+                etag: String::from(etag),
+                headers: resp
+                    .headers
+                    .iter()
+                    .filter_map(|(name, v)| Some((name.to_string(), v.to_str().ok()?.to_owned())))
+                    .collect(),
+                body: resp.body.clone(),
+            };
+            if let Err(e) = cache.put(&entry) {
+                warn!("Can not cache the response of {}: {}", url, e);
+            }
+        }
         Ok(resp)
     }
 
-    fn events_page_request(&self, page: u8) -> Result<(Vec<Event>, bool), String> {
+    // login of the token owner and scopes granted to the token
+    fn authenticated_user(&self) -> Result<(String, Option<Vec<String>>), StandupError> {
+        let resp = self.request(&format!("{}/user", self.api_url))?;
+        let scopes = resp
+            .headers
+            .get("X-OAuth-Scopes")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| {
+                v.split(',')
+                    .map(|s| String::from(s.trim()))
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            });
+        let user: User = parse_json(&resp)?;
+        Ok((user.login, scopes))
+    }
+
+    // github silently falls back to public events, so misconfiguration is reported upfront
+    fn check_private_access(&self) -> Result<(), StandupError> {
+        let (login, scopes) = self.authenticated_user()?;
+        if !login.eq_ignore_ascii_case(self.user) {
+            return Err(StandupError::Auth(format!(
+                "--include-private requires a token of {}, the token belongs to {}",
+                self.user, login
+            )));
+        }
+        if let Some(scopes) = scopes {
+            if !scope_granted(&scopes, "repo") {
+                warn!("The token doesn't have the repo scope, private events may be missing");
+            }
+        }
+        Ok(())
+    }
+
+    fn graphql<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, StandupError> {
+        let url = graphql_url(self.api_url);
+        debug!("POST {}", url);
+        let resp = http::client()?
+            .post(&url)
+            .header(AUTHORIZATION, format!("token {}", self.token))
+            .json(&serde_json::json!({ "query": query, "variables": variables }))
+            .send()?;
+        let resp = ApiResponse::read(check_status(resp)?)?;
+
+        let json: GraphQLResp<T> = parse_json(&resp)?;
+        // graphql api responds with 200 even if the query failed
+        if !json.errors.is_empty() {
+            let messages: Vec<String> = json.errors.into_iter().map(|e| e.message).collect();
+            return Err(StandupError::Api(format!(
+                "Github query failed: {}",
+                messages.join(", ")
+            )));
+        }
+
+        json.data
+            .ok_or_else(|| StandupError::Api(String::from("Github query returned no data")))
+    }
+
+    // events are returned as raw json values so they can be saved into the progress file
+    fn events_page_request(
+        &self,
+        page: u8,
+    ) -> Result<(Vec<serde_json::Value>, bool, Option<RateLimit>), StandupError> {
+        // the feed includes private events only if the token belongs to the user
         // documentation says per_page isn't supported but it is :-D
-        let mut resp = self.request(&format!(
-            "https://api.github.com/users/{}/events?page={}&per_page=100",
-            self.user, page,
+        let resp = self.request(&format!(
+            "{}/users/{}/events?page={}&per_page=100",
+            self.api_url, self.user, page,
         ))?;
 
-        let events: Vec<Event> = resp
-            .json()
-            .map_err(|e| format!("Can not parse Github response: {}", e))?;
+        let events: Vec<serde_json::Value> = parse_json(&resp)?;
+        let headers = &resp.headers;
 
-        Ok((events, Self::has_next_page(resp.headers())))
+        Ok((
+            events,
+            Self::has_next_page(headers),
+            RateLimit::from_headers(headers),
+        ))
     }
 
     fn has_next_page(headers: &HeaderMap) -> bool {
@@ -244,8 +1053,108 @@ impl GithubApi<'_> {
     }
 }
 
+// headers of every response to the REST API
+#[derive(Clone, Copy)]
+struct RateLimit {
+    remaining: u64,
+    reset: DateTime<Utc>,
+}
+
+// below this many requests left, pages are spread over the time until the reset
+const RATE_LIMIT_LOW: u64 = 100;
+const RATE_LIMIT_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(10);
+
+impl RateLimit {
+    fn from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+        let header = |name| headers.get(name)?.to_str().ok()?.parse::<i64>().ok();
+        let remaining = header("x-ratelimit-remaining")?;
+        let reset = Utc
+            .timestamp_opt(header("x-ratelimit-reset")?, 0)
+            .single()?;
+        Some(RateLimit {
+            remaining: remaining.max(0) as u64,
+            reset,
+        })
+    }
+
+    // the closer the limit, the longer the wait, but never longer than the cap
+    fn delay(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+        if self.remaining >= RATE_LIMIT_LOW {
+            return None;
+        }
+        let until_reset = (self.reset - now).to_std().ok()?;
+        let delay = until_reset / (self.remaining as u32 + 1);
+        Some(delay.min(RATE_LIMIT_MAX_DELAY))
+    }
+}
+
+const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+// longer waits would look like a hang, the request fails instead
+const RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+// None if the response isn't worth retrying. Server errors and rate limits
+// are retried after Retry-After, the rate limit reset or an exponential backoff.
+// A 403 is a rate limit only with these headers, otherwise it means no access
+fn retry_delay(
+    status: StatusCode,
+    headers: &HeaderMap,
+    attempt: u32,
+    now: DateTime<Utc>,
+) -> Option<std::time::Duration> {
+    let retry_after = headers
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(std::time::Duration::from_secs);
+    let exhausted = RateLimit::from_headers(headers).filter(|r| r.remaining == 0);
+    let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && (retry_after.is_some() || exhausted.is_some()));
+    if !status.is_server_error() && !rate_limited {
+        return None;
+    }
+
+    let delay = match (retry_after, exhausted) {
+        (Some(delay), _) => delay,
+        (None, Some(r)) => (r.reset - now).to_std().unwrap_or_default(),
+        (None, None) => RETRY_BASE_DELAY * 2u32.saturating_pow(attempt),
+    };
+    Some(delay).filter(|d| *d <= RETRY_MAX_DELAY)
+}
+
 // Transformations
 
+// owner/name is compared as a whole, a bare name matches the repository of any owner
+fn repo_matches(pattern: &str, repo: &str) -> bool {
+    if pattern.contains('/') {
+        return pattern.eq_ignore_ascii_case(repo);
+    }
+    repo.rsplit('/')
+        .next()
+        .is_some_and(|name| name.eq_ignore_ascii_case(pattern))
+}
+
+// exclusions apply after inclusions, so both can be combined
+fn keep_repo(opts: &Options, repo: &str) -> bool {
+    (opts.repos.is_empty() || opts.repos.iter().any(|p| repo_matches(p, repo)))
+        && !opts.exclude_repos.iter().any(|p| repo_matches(p, repo))
+}
+
+// applied before enrichment so dropped repositories cost no requests,
+// a --repo matching none of the events is likely a typo
+fn filter_repos(events: &mut Vec<Event>, opts: &Options) {
+    for pattern in &opts.repos {
+        if !events.iter().any(|e| repo_matches(pattern, &e.repo.name)) {
+            warn!("--repo {} matched no repository", pattern);
+        }
+    }
+    events.retain(|e| keep_repo(opts, &e.repo.name));
+}
+
+// only PRs target a branch, issues, commits and wiki pages are kept
+fn keep_base_branch(pattern: &str, entry: &Entry) -> bool {
+    entry.r#type != "PR" || entry.base.as_ref().is_none_or(|b| glob_match(pattern, b))
+}
+
 fn group_by_repos(events: &[Event]) -> HashMap<&String, Vec<&Event>> {
     let mut res = HashMap::new();
 
@@ -257,31 +1166,56 @@ fn group_by_repos(events: &[Event]) -> HashMap<&String, Vec<&Event>> {
     res
 }
 
-fn convert(
-    login: &str,
-    issue_comments: bool,
-    events: &[&EventPayload],
-) -> Result<Vec<Entry>, String> {
+// notes left on own PRs, added to the entry even if it was opened or pushed to
+fn self_review(res: &mut HashMap<u64, Entry>, pr: &PullRequest) {
+    let entry = res.entry(pr.number).or_insert_with(|| pr.entry(Vec::new()));
+    let action = String::from("self-reviewed");
+    if !entry.actions.contains(&action) {
+        entry.actions.push(action);
+    }
+}
+
+// logins are all identities of the user, github logins are case-insensitive
+fn convert(logins: &[&str], opts: &Options, events: &[&Event]) -> Result<Vec<Entry>, StandupError> {
+    let is_me = |user: &User| logins.iter().any(|l| l.eq_ignore_ascii_case(&user.login));
     let mut res = HashMap::new();
+    // wiki pages don't have numbers, they are keyed by url
+    let mut pages: HashMap<&str, Entry> = HashMap::new();
+    // pushes without a pull request are keyed by ref, shas count distinct commits
+    let mut branches: HashMap<&str, (Entry, HashSet<&str>)> = HashMap::new();
 
     for event in events {
-        match event {
+        let payload = match &event.payload {
+            Some(p) => p,
+            None => continue,
+        };
+
+        match payload {
             EventPayload::PullRequest(p) => {
                 let pr = &p.pull_request;
-                let entry = res.entry(pr.number).or_insert(Entry {
-                    r#type: String::from("PR"),
-                    title: pr.title.clone(),
-                    url: Some(pr.html_url.clone()),
-                    actions: Vec::new(),
-                });
+                let entry = res.entry(pr.number).or_insert_with(|| pr.entry(Vec::new()));
 
                 let mut action = p.action.clone();
-                if action == "closed" && pr.merged {
-                    action = if login != pr.user.login {
-                        String::from("reviewed")
-                    } else {
-                        String::from("merged")
-                    }
+                let merged = action == "closed" && (pr.merged || pr.merged_at.is_some());
+                if merged {
+                    entry.sha = pr
+                        .merge_commit_sha
+                        .as_ref()
+                        .map(|s| s.chars().take(7).collect());
+                }
+                let base = pr.base.as_ref().map(|b| b.r#ref.as_str());
+                if merged && base.is_some_and(|b| opts.deploy_branches.iter().any(|d| d == b)) {
+                    action = String::from("deployed");
+                } else if merged {
+                    let authored = is_me(&pr.user);
+                    // events payload may miss merged_by, the actor of the event is me then
+                    let merged_by_me = pr.merged_by.as_ref().is_none_or(is_me);
+                    action = String::from(match (authored, merged_by_me) {
+                        (true, true) => "authored & merged",
+                        (true, false) => "merged (mine, by other)",
+                        (false, true) => "merged",
+                        (false, false) => "reviewed",
+                    });
                 }
                 // can be pushes before opening a PR, skip them
                 if action == "opened" {
@@ -297,16 +1231,15 @@ fn convert(
                 }
 
                 let pr = &p.pull_request;
-                if pr.user.login == login {
+                if is_me(&pr.user) {
+                    if opts.own_reviews {
+                        self_review(&mut res, pr);
+                    }
                     continue;
                 }
 
-                res.entry(pr.number).or_insert(Entry {
-                    r#type: String::from("PR"),
-                    title: pr.title.clone(),
-                    url: Some(pr.html_url.clone()),
-                    actions: vec![String::from("reviewed")],
-                });
+                res.entry(pr.number)
+                    .or_insert_with(|| pr.entry(vec![String::from("reviewed")]));
             }
             EventPayload::ReviewComment(p) => {
                 if p.action != "created" {
@@ -314,16 +1247,15 @@ fn convert(
                 }
 
                 let pr = &p.pull_request;
-                if pr.user.login == login {
+                if is_me(&pr.user) {
+                    if opts.own_reviews {
+                        self_review(&mut res, pr);
+                    }
                     continue;
                 }
 
-                res.entry(pr.number).or_insert(Entry {
-                    r#type: String::from("PR"),
-                    title: pr.title.clone(),
-                    url: Some(pr.html_url.clone()),
-                    actions: vec![String::from("reviewed")],
-                });
+                res.entry(pr.number)
+                    .or_insert_with(|| pr.entry(vec![String::from("reviewed")]));
             }
             EventPayload::Issue(p) => {
                 if p.action != "opened" {
@@ -331,12 +1263,9 @@ fn convert(
                 }
 
                 let issue = &p.issue;
-                let entry = res.entry(issue.number).or_insert(Entry {
-                    r#type: String::from("Issue"),
-                    title: issue.title.clone(),
-                    url: Some(issue.html_url.clone()),
-                    actions: Vec::new(),
-                });
+                let entry = res
+                    .entry(issue.number)
+                    .or_insert_with(|| issue.entry("Issue", Vec::new()));
 
                 if !entry.actions.contains(&p.action) {
                     entry.actions.push(p.action.clone());
@@ -357,56 +1286,164 @@ fn convert(
                     .nth(5)
                     .expect("url must be parsable");
                 if entity_type == "pull" {
-                    if issue.user.login == login {
+                    if is_me(&issue.user) {
                         continue;
                     }
 
-                    res.entry(issue.number).or_insert(Entry {
-                        r#type: String::from("PR"),
-                        title: issue.title.clone(),
-                        url: Some(issue.html_url.clone()),
-                        actions: vec![String::from("reviewed")],
-                    });
-                    continue;
-                }
-
-                if !issue_comments || res.contains_key(&issue.number) {
-                    continue;
+                    res.entry(issue.number)
+                        .or_insert_with(|| issue.entry("PR", vec![String::from("reviewed")]));
+                } else {
+                    if !opts.issue_comments || res.contains_key(&issue.number) {
+                        continue;
+                    }
+                    // comments on my own issues are usually answers to others
+                    let action = if is_me(&issue.user) {
+                        "replied"
+                    } else {
+                        "commented"
+                    };
+                    res.insert(
+                        issue.number,
+                        issue.entry("Issue", vec![String::from(action)]),
+                    );
                 }
-                res.insert(
-                    issue.number,
-                    Entry {
-                        r#type: String::from("Issue"),
-                        title: issue.title.clone(),
-                        url: Some(issue.html_url.clone()),
-                        actions: vec![String::from("commented")],
-                    },
-                );
             }
             EventPayload::Push(p) => {
                 if let Some(prs) = &p.pull_requests {
                     for pr in prs {
                         // insert Entry only if this PR doesn't exist in the history yet
                         // to avoid pushed actions for just opened PRs
-                        res.entry(pr.number).or_insert(Entry {
-                            r#type: String::from("PR"),
-                            title: pr.title.clone(),
-                            url: Some(pr.html_url.clone()),
-                            actions: vec![String::from("pushed")],
-                        });
+                        res.entry(pr.number)
+                            .or_insert_with(|| pr.entry(vec![String::from("pushed")]));
                     }
+                    continue;
+                }
+                // without the lookup it's unknown whether the branch has a PR
+                let branch = match p.r#ref.strip_prefix("refs/heads/") {
+                    Some(b) if opts.enrich && b != "master" && !p.is_merge_only() => b,
+                    _ => continue,
+                };
+                let first = match p.commits.first() {
+                    Some(c) => c.message.lines().next().unwrap_or_default(),
+                    None => continue,
+                };
+                let (entry, shas) = branches.entry(&p.r#ref).or_insert_with(|| {
+                    let entry = Entry {
+                        r#type: String::from("Commit"),
+                        title: format!("{}: {}", branch, first),
+                        url: Some(format!(
+                            "{}/{}/commits/{}",
+                            web_url(&opts.api_url),
+                            event.repo.name,
+                            branch
+                        )),
+                        actions: vec![String::from("pushed")],
+                        ..Default::default()
+                    };
+                    (entry, HashSet::new())
+                });
+                shas.extend(p.commits.iter().map(|c| c.sha.as_str()));
+                entry.sha = p.commits.last().map(|c| c.sha.chars().take(7).collect());
+                entry.created_at = Some(event.created_at);
+            }
+            EventPayload::Gollum(p) => {
+                if !opts.include_wiki {
+                    continue;
+                }
+                for page in &p.pages {
+                    let entry = pages.entry(&page.html_url).or_insert_with(|| Entry {
+                        r#type: String::from("Wiki"),
+                        title: page.title.clone(),
+                        url: Some(page.html_url.clone()),
+                        ..Default::default()
+                    });
+                    if !entry.actions.contains(&page.action) {
+                        entry.actions.push(page.action.clone());
+                    }
+                    entry.created_at = Some(event.created_at);
                 }
             }
         }
+
+        // skipped events don't reach this point, events are sorted
+        // so entries end up with the time of the latest activity
+        for number in payload.numbers() {
+            if let Some(entry) = res.get_mut(&number) {
+                entry.created_at = Some(event.created_at);
+            }
+        }
+        for (number, count) in payload.comment_counts() {
+            if let Some(entry) = res.get_mut(&number) {
+                entry.comments = Some(count);
+            }
+        }
     }
 
-    Ok(res.values().cloned().collect())
+    let commits = branches.into_values().map(|(mut entry, shas)| {
+        if shas.len() > 1 {
+            entry.title.push_str(&format!(" ({} commits)", shas.len()));
+        }
+        entry
+    });
+    Ok(res
+        .into_values()
+        .chain(pages.into_values())
+        .chain(commits)
+        .collect())
 }
 
-fn enhance_events(gh: &GithubApi, events: &mut Vec<Event>) -> Result<(), String> {
-    // try to find pull requests for push events
-    let mut repo_cache = HashMap::new();
-    let mut checked_refs = HashSet::new();
+// repositories are cached by name, missing ones as None
+type RepoCache = HashMap<String, Option<Repo>>;
+
+fn cached_repo<'c>(
+    gh: &GithubApi,
+    repo_cache: &'c mut RepoCache,
+    name: &str,
+) -> Result<Option<&'c Repo>, StandupError> {
+    if !repo_cache.contains_key(name) {
+        let r = gh.get_repo(name)?;
+        repo_cache.insert(String::from(name), r);
+    }
+    Ok(repo_cache[name].as_ref())
+}
+
+fn is_archived(
+    gh: &GithubApi,
+    repo_cache: &mut RepoCache,
+    name: &str,
+) -> Result<bool, StandupError> {
+    Ok(cached_repo(gh, repo_cache, name)?.is_some_and(|r| r.archived))
+}
+
+// events in forks are moved to the upstream repository,
+// the same way pushes matched to upstream pull requests are
+fn canonicalize_forks(
+    gh: &GithubApi,
+    events: &mut [Event],
+    repo_cache: &mut RepoCache,
+) -> Result<(), StandupError> {
+    for e in events {
+        let source = cached_repo(gh, repo_cache, &e.repo.name)?.and_then(|r| r.source.as_ref());
+        if let Some(source) = source {
+            debug!(
+                "moving event {} from {} to {}",
+                e.id, e.repo.name, source.full_name
+            );
+            e.repo.name = source.full_name.clone();
+        }
+    }
+    Ok(())
+}
+
+fn enhance_events(
+    gh: &GithubApi,
+    events: &mut Vec<Event>,
+    repo_cache: &mut RepoCache,
+    skip_archived: bool,
+) -> Result<(), StandupError> {
+    // try to find pull requests for push events,
+    // refs are looked up once and keep the repository prs were found in
+    let mut checked_refs: HashMap<String, (String, Vec<PullRequest>)> = HashMap::new();
     for e in events {
         if let Some(EventPayload::Push(p)) = e.payload.as_mut() {
             // even prs _can_ be opened from master, I don't do that
@@ -414,37 +1451,77 @@ fn enhance_events(gh: &GithubApi, events: &mut Vec<Event>) -> Result<(), String>
             if p.r#ref == "refs/heads/master" {
                 continue;
             }
+            // merging master into a branch isn't work worth reporting
+            if p.is_merge_only() {
+                debug!("skipping merge-only push to {} {}", e.repo.name, p.r#ref);
+                continue;
+            }
 
-            let repo_name = &e.repo.name;
-            if !checked_refs.insert(format!("{}_{}", repo_name, p.r#ref)) {
+            let key = format!("{}_{}", e.repo.name, p.r#ref);
+            if let Some((repo_name, prs)) = checked_refs.get(&key) {
+                e.repo.name = repo_name.clone();
+                if !prs.is_empty() {
+                    p.pull_requests = Some(prs.clone());
+                }
                 continue;
             }
+            checked_refs.insert(key.clone(), (e.repo.name.clone(), Vec::new()));
+            let repo_name = &e.repo.name;
             // events contain only repo name but we need source as well for forks
             let repo = match repo_cache.get(repo_name) {
-                Some(r) => &r,
+                Some(r) => {
+                    debug!("repo cache hit for {}", repo_name);
+                    r
+                }
                 None => {
                     let r = gh.get_repo(repo_name)?;
+                    if r.is_none() {
+                        warn!(
+                            "Repository {} is gone, skipping pull requests lookup",
+                            repo_name
+                        );
+                    }
+                    // missing repos are cached too, so they aren't requested for every ref
                     repo_cache.insert(String::from(repo_name), r);
                     // FIXME there must be better way to do it without violation of lifetime
                     repo_cache.get(repo_name).unwrap()
                 }
             };
+            let repo = match repo {
+                Some(r) => r,
+                None => continue,
+            };
+            if skip_archived && repo.archived {
+                debug!("skipping pull requests lookup in archived {}", repo_name);
+                continue;
+            }
 
-            let owner = &repo.full_name.split('/').nth(0).unwrap();
+            let owner = &repo.full_name.split('/').next().unwrap();
             let head = format!("{}:{}", owner, p.r#ref);
             // try to find PR in source repo if push was made to fork
             let prs = if let Some(source) = &repo.source {
-                let prs = gh.find_prs(&source.full_name, &head)?;
-                // change source of the event to pr's repository
-                e.repo.name = source.full_name.clone();
-                prs
+                match gh.find_prs(&source.full_name, &head)? {
+                    Some(prs) => {
+                        // change source of the event to pr's repository
+                        e.repo.name = source.full_name.clone();
+                        prs
+                    }
+                    None => {
+                        warn!(
+                            "Can not access {}, skipping pull requests lookup for {}",
+                            source.full_name, head,
+                        );
+                        continue;
+                    }
+                }
             // for non-forks try to find in the repo itself
             } else {
-                gh.find_prs(&repo.full_name, &head)?
+                gh.find_prs(&repo.full_name, &head)?.unwrap_or_default()
             };
             // TODO: it is possible that PR can be make to a fork
 
             if !prs.is_empty() {
+                checked_refs.insert(key, (e.repo.name.clone(), prs.clone()));
                 p.pull_requests = Some(prs);
             }
         }
@@ -453,35 +1530,768 @@ fn enhance_events(gh: &GithubApi, events: &mut Vec<Event>) -> Result<(), String>
     Ok(())
 }
 
+// project items don't belong to a repository, they are grouped by project
+fn project_items(
+    gh: &GithubApi,
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+) -> Result<HashMap<String, Vec<Entry>>, StandupError> {
+    let data: ProjectsData = gh.graphql(PROJECTS_QUERY, serde_json::json!({}))?;
+
+    let mut res = HashMap::new();
+    for project in data.viewer.projects_v2.nodes {
+        let entries: Vec<Entry> = project
+            .items
+            .nodes
+            .into_iter()
+            .filter(|x| x.updated_at >= since && until.is_none_or(|d| x.updated_at < d))
+            .filter_map(|x| x.content)
+            .map(|content| {
+                let (title, url, number) = match content {
+                    ProjectItemContent::DraftIssue { title } => (title, None, None),
+                    ProjectItemContent::Issue { title, url, number }
+                    | ProjectItemContent::PullRequest { title, url, number } => {
+                        (title, Some(url), Some(number))
+                    }
+                };
+                Entry {
+                    r#type: String::from("Project item"),
+                    number,
+                    title,
+                    url,
+                    actions: vec![String::from("updated")],
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        if !entries.is_empty() {
+            res.insert(format!("Project: {}", project.title), entries);
+        }
+    }
+
+    Ok(res)
+}
+
+// discussions started within the window are opened, others were updated by
+// a comment that may be someone else's, there is no way to tell by search
+fn discussions(
+    gh: &GithubApi,
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+) -> Result<HashMap<String, Vec<Entry>>, StandupError> {
+    let window = |d: DateTime<Utc>| d >= since && until.is_none_or(|u| d < u);
+    let updated = format!("updated:>={}", since.format("%Y-%m-%d"));
+    let data: DiscussionsData = gh.graphql(
+        DISCUSSIONS_QUERY,
+        serde_json::json!({
+            "authored": format!("author:{} {}", gh.user, updated),
+            "commented": format!("commenter:{} {}", gh.user, updated),
+        }),
+    )?;
+
+    let opened = data
+        .authored
+        .nodes
+        .into_iter()
+        .filter(|d| window(d.created_at))
+        .map(|d| (d, "opened"));
+    let commented = data
+        .commented
+        .nodes
+        .into_iter()
+        .filter(|d| window(d.updated_at))
+        .map(|d| (d, "commented"));
+
+    let mut seen = HashSet::new();
+    let mut res: HashMap<String, Vec<Entry>> = HashMap::new();
+    for (d, action) in opened.chain(commented) {
+        if !seen.insert(d.url.clone()) {
+            continue;
+        }
+        res.entry(d.repository.name_with_owner)
+            .or_default()
+            .push(Entry {
+                r#type: String::from("Discussion"),
+                number: Some(d.number),
+                title: d.title,
+                url: Some(d.url),
+                actions: vec![String::from(action)],
+                created_at: Some(d.updated_at),
+                ..Default::default()
+            });
+    }
+
+    Ok(res)
+}
+
+#[derive(Clone)]
+pub struct Options {
+    // other logins of the user, e.g. a renamed account or a bot
+    pub aliases: Vec<String>,
+    pub issue_comments: bool,
+    // events slightly outside of the window are included to compensate
+    // for the difference between event timestamps and local midnight
+    pub since_buffer: Duration,
+    pub progress_file: PathBuf,
+    // continue interrupted fetch using progress_file
+    pub resume: bool,
+    // fail instead of warning about incomplete or unparsable events
+    pub strict: bool,
+    // look up pull requests for push events, costs extra requests
+    pub enrich: bool,
+    // add items of Github Projects updated within the window
+    pub include_projects: bool,
+    // add discussions opened or commented within the window
+    pub include_discussions: bool,
+    // keep excerpts of PRs and issues descriptions
+    pub with_body: bool,
+    // report activity in forks under the upstream repository
+    pub merge_forks: bool,
+    // drop repositories that are archived, costs a request per repository
+    pub skip_archived: bool,
+    // actions sorted first within a repository
+    pub action_priority: Vec<String>,
+    // fallback tokens for repositories and pull requests lookups
+    pub extra_tokens: Vec<String>,
+    // add wiki pages created or edited
+    pub include_wiki: bool,
+    // keep issues closed by PRs
+    pub with_closed_issues: bool,
+    // keep short merge commit shas of merged PRs
+    pub with_sha: bool,
+    // keep numbers of comments known from events
+    pub with_comment_counts: bool,
+    // merges into these branches are reported as deployed
+    pub deploy_branches: Vec<String>,
+    // report reviews and review comments on own PRs as self-reviewed
+    pub own_reviews: bool,
+    // keep only PRs with more changed lines, costs a request per PR
+    pub min_changes: Option<u64>,
+    // count approvals of own PRs, costs a request per PR
+    pub with_approvals: bool,
+    // keep only PRs and issues with this label
+    pub label: Option<String>,
+    // keep only PRs with a matching base branch, e.g. release/*
+    pub base_branch: Option<String>,
+    // check that the token can see events of private repositories
+    pub include_private: bool,
+    // stop fetching after this many events instead of relying on since
+    pub last: Option<usize>,
+    // keep only these repositories, owner/name or just name
+    pub repos: Vec<String>,
+    // drop these repositories, matched the same way as repos
+    pub exclude_repos: Vec<String>,
+    // retries of requests failing with 5xx or a rate limit
+    pub max_retries: u32,
+    // responses are cached here, None with --no-cache
+    pub cache_dir: Option<PathBuf>,
+    // base of the REST API, see api_url()
+    pub api_url: String,
+}
+
 pub fn fetch(
     user: &str,
     token: &str,
     since: DateTime<Utc>,
     until: Option<DateTime<Utc>>,
-    issue_comments: bool,
-) -> Result<HashMap<String, Vec<Entry>>, String> {
-    let gh = GithubApi { user, token };
+    opts: &Options,
+) -> Result<HashMap<String, Vec<Entry>>, StandupError> {
+    let gh = GithubApi {
+        user,
+        token,
+        extra_tokens: &opts.extra_tokens,
+        max_retries: opts.max_retries,
+        cache: opts.cache_dir.clone().map(Cache::new),
+        api_url: &opts.api_url,
+    };
+
+    if opts.include_private {
+        gh.check_private_access()?;
+    }
 
-    let mut events: Vec<Event> = gh.events(since, until)?;
+    let since = since - opts.since_buffer;
+    let until = until.map(|d| d + opts.since_buffer);
+    let mut events: Vec<Event> = gh.events(since, until, opts)?;
+    filter_repos(&mut events, opts);
     // enrich events with additional information
-    enhance_events(&gh, &mut events)?;
+    let mut repo_cache = HashMap::new();
+    if opts.enrich {
+        enhance_events(&gh, &mut events, &mut repo_cache, opts.skip_archived)?;
+    }
+    if opts.merge_forks {
+        canonicalize_forks(&gh, &mut events, &mut repo_cache)?;
+    }
     // converting requires events to be sorted by date
     events.sort_by_key(|x| x.created_at);
 
+    let mut logins = vec![user];
+    logins.extend(opts.aliases.iter().map(String::as_str));
+
     let mut result = HashMap::new();
     for (repo, events) in group_by_repos(&events) {
-        let payloads: Vec<&EventPayload> = events
-            .into_iter()
-            .map(|x| x.payload.as_ref())
-            .flatten()
-            .collect();
-
-        let events = convert(user, issue_comments, &payloads)?;
+        if opts.skip_archived && is_archived(&gh, &mut repo_cache, repo)? {
+            debug!("skipping archived repository {}", repo);
+            continue;
+        }
+        let mut events = convert(&logins, opts, &events)?;
+        if let Some(label) = &opts.label {
+            events.retain(|e| e.labels.iter().any(|l| l.eq_ignore_ascii_case(label)));
+        }
+        if let Some(pattern) = &opts.base_branch {
+            for e in events
+                .iter_mut()
+                .filter(|e| e.r#type == "PR" && e.base.is_none())
+            {
+                if let Some(number) = e.number {
+                    e.base = gh.pull_base(repo, number)?;
+                }
+            }
+            events.retain(|e| keep_base_branch(pattern, e));
+        }
+        if !opts.with_body {
+            events.iter_mut().for_each(|e| e.body = None);
+        }
+        if !opts.with_comment_counts {
+            events.iter_mut().for_each(|e| e.comments = None);
+        }
+        if !opts.with_closed_issues {
+            events.iter_mut().for_each(|e| e.closes.clear());
+        }
+        if !opts.with_sha {
+            events.iter_mut().for_each(|e| e.sha = None);
+        }
+        // target branches are kept only when PRs are filtered or deploys reported by them
+        if opts.base_branch.is_none() && opts.deploy_branches.is_empty() {
+            events.iter_mut().for_each(|e| e.base = None);
+        }
+        if let Some(min) = opts.min_changes {
+            let mut kept = Vec::new();
+            for e in events {
+                match (e.r#type.as_str(), e.number) {
+                    ("PR", Some(number)) if gh.pull_changes(repo, number)? <= min => {
+                        debug!("skipping small PR {}#{}", repo, number)
+                    }
+                    _ => kept.push(e),
+                }
+            }
+            events = kept;
+        }
+        sort_entries(&mut events, &opts.action_priority);
+        if opts.with_approvals {
+            for e in events.iter_mut() {
+                let authored = e
+                    .author
+                    .as_ref()
+                    .is_some_and(|a| logins.iter().any(|l| l.eq_ignore_ascii_case(a)));
+                if let (true, "PR", Some(number)) = (authored, e.r#type.as_str(), e.number) {
+                    e.reviews = Some(count_reviews(&gh.reviews(repo, number)?));
+                }
+            }
+        }
 
         if !events.is_empty() {
             result.insert(repo.clone(), events);
         }
     }
 
+    if opts.include_discussions {
+        for (repo, entries) in discussions(&gh, since, until)? {
+            if !keep_repo(opts, &repo) {
+                continue;
+            }
+            result.entry(repo).or_default().extend(entries);
+        }
+    }
+    if opts.include_projects {
+        result.extend(project_items(&gh, since, until)?);
+    }
+
     Ok(result)
 }
+
+pub const API_URL: &str = "https://api.github.com";
+
+// configured base of the REST API without the trailing slash, github.com by default
+pub fn api_url(configured: Option<&str>) -> String {
+    String::from(configured.map_or(API_URL, |url| url.trim_end_matches('/')))
+}
+
+// links to pages of github.com or the Enterprise Server the API belongs to
+fn web_url(api_url: &str) -> String {
+    if api_url == API_URL {
+        return String::from("https://github.com");
+    }
+    String::from(api_url.strip_suffix("/api/v3").unwrap_or(api_url))
+}
+
+// GitHub Enterprise Server serves GraphQL at /api/graphql next to /api/v3
+fn graphql_url(api_url: &str) -> String {
+    format!("{}/graphql", api_url.strip_suffix("/v3").unwrap_or(api_url))
+}
+
+// scopes of classic tokens and what they are needed for,
+// the public events feed itself doesn't require any
+pub const TOKEN_SCOPES: &[(&str, &str)] = &[
+    ("repo", "pull requests and events of private repositories"),
+    ("read:project", "--include-projects"),
+];
+
+// None if the token doesn't report scopes, e.g. fine-grained tokens
+pub fn token_scopes(
+    user: &str,
+    token: &str,
+    api_url: &str,
+) -> Result<Option<Vec<String>>, StandupError> {
+    // checks are expected to answer quickly
+    let gh = GithubApi {
+        user,
+        token,
+        extra_tokens: &[],
+        max_retries: 0,
+        cache: None,
+        api_url,
+    };
+    let (_, scopes) = gh.authenticated_user()?;
+    Ok(scopes)
+}
+
+// write scopes include the read ones
+pub fn scope_granted(scopes: &[String], scope: &str) -> bool {
+    scopes
+        .iter()
+        .any(|s| s == scope || (scope == "read:project" && s == "project"))
+}
+
+// open PRs authored by the user and open issues assigned to them,
+// the search api returns at most 100 items per query here
+pub fn fetch_todo(
+    user: &str,
+    token: &str,
+    api_url: &str,
+    max_retries: u32,
+) -> Result<Vec<Entry>, StandupError> {
+    let gh = GithubApi {
+        user,
+        token,
+        extra_tokens: &[],
+        max_retries,
+        cache: None,
+        api_url,
+    };
+    let queries = [
+        format!("is:open is:pr archived:false author:{}", user),
+        format!("is:open is:issue archived:false assignee:{}", user),
+    ];
+
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    for query in &queries {
+        for item in gh.search_issues(query)? {
+            if !seen.insert(item.issue.html_url.clone()) {
+                continue;
+            }
+            let r#type = if item.pull_request.is_some() {
+                "PR"
+            } else {
+                "Issue"
+            };
+            entries.push(item.issue.entry(r#type, vec![String::from("open")]));
+        }
+    }
+    Ok(entries)
+}
+
+pub type TeamResult = Result<HashMap<String, Vec<Entry>>, StandupError>;
+
+// fetches activity of other users, at most `concurrency` of them at the same time
+// to stay within GitHub rate limits. Results keep the order of users
+pub fn fetch_team(
+    users: &[String],
+    token: &str,
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+    opts: &Options,
+    concurrency: usize,
+) -> Vec<(String, TeamResult)> {
+    let queue = Mutex::new(users.iter().enumerate());
+    let results = Mutex::new(Vec::new());
+    thread::scope(|s| {
+        for _ in 0..concurrency.clamp(1, users.len().max(1)) {
+            s.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let (i, user) = match next {
+                    Some(v) => v,
+                    None => break,
+                };
+                // aliases, projects and private events belong to the owner of the token
+                let opts = Options {
+                    aliases: Vec::new(),
+                    include_projects: false,
+                    include_private: false,
+                    progress_file: PathBuf::from(format!(
+                        "{}-{}",
+                        opts.progress_file.display(),
+                        user
+                    )),
+                    ..opts.clone()
+                };
+                let result = fetch(user, token, since, until, &opts);
+                results.lock().unwrap().push((i, user.clone(), result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _, _)| *i);
+    results
+        .into_iter()
+        .map(|(_, user, result)| (user, result))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_patterns() {
+        assert!(glob_match("master", "master"));
+        assert!(!glob_match("master", "master2"));
+        assert!(glob_match("release/*", "release/1.0"));
+        assert!(glob_match("release/*", "release/"));
+        assert!(!glob_match("release/*", "releases/1.0"));
+        assert!(glob_match("*-hotfix", "v1-hotfix"));
+        assert!(glob_match("v?.x", "v1.x"));
+        assert!(!glob_match("v?.x", "v10.x"));
+        assert!(glob_match("*", ""));
+    }
+
+    fn push(messages: &[&str]) -> PushPayload {
+        PushPayload {
+            r#ref: String::from("refs/heads/feature"),
+            commits: messages
+                .iter()
+                .map(|m| PushCommit {
+                    sha: m.bytes().map(|b| format!("{:02x}", b)).collect(),
+                    message: String::from(*m),
+                })
+                .collect(),
+            pull_requests: None,
+        }
+    }
+
+    fn event(repo: &str) -> Event {
+        Event {
+            id: String::from("1"),
+            repo: EventRepo {
+                name: String::from(repo),
+            },
+            payload: None,
+            created_at: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+        }
+    }
+
+    fn options() -> Options {
+        Options {
+            aliases: Vec::new(),
+            issue_comments: false,
+            since_buffer: Duration::zero(),
+            progress_file: PathBuf::new(),
+            resume: false,
+            strict: false,
+            enrich: false,
+            include_projects: false,
+            include_discussions: false,
+            with_body: false,
+            merge_forks: false,
+            skip_archived: false,
+            action_priority: Vec::new(),
+            extra_tokens: Vec::new(),
+            include_wiki: false,
+            with_closed_issues: false,
+            with_sha: false,
+            with_comment_counts: false,
+            deploy_branches: Vec::new(),
+            own_reviews: false,
+            min_changes: None,
+            with_approvals: false,
+            label: None,
+            base_branch: None,
+            include_private: false,
+            last: None,
+            repos: Vec::new(),
+            exclude_repos: Vec::new(),
+            max_retries: 0,
+            cache_dir: None,
+            api_url: String::from(API_URL),
+        }
+    }
+
+    #[test]
+    fn filters_repos_by_full_or_short_name() {
+        let names = |events: &[Event]| -> Vec<String> {
+            events.iter().map(|e| e.repo.name.clone()).collect()
+        };
+        let all = || vec![event("org/api"), event("org/web"), event("me/API")];
+
+        let mut events = all();
+        filter_repos(&mut events, &options());
+        assert_eq!(names(&events), vec!["org/api", "org/web", "me/API"]);
+
+        let mut events = all();
+        let opts = Options {
+            repos: vec![String::from("api")],
+            ..options()
+        };
+        filter_repos(&mut events, &opts);
+        assert_eq!(names(&events), vec!["org/api", "me/API"]);
+
+        let mut events = all();
+        let opts = Options {
+            repos: vec![String::from("ORG/Web"), String::from("typo")],
+            ..options()
+        };
+        filter_repos(&mut events, &opts);
+        assert_eq!(names(&events), vec!["org/web"]);
+
+        let mut events = all();
+        let opts = Options {
+            repos: vec![String::from("api")],
+            exclude_repos: vec![String::from("me/api")],
+            ..options()
+        };
+        filter_repos(&mut events, &opts);
+        assert_eq!(names(&events), vec!["org/api"]);
+    }
+
+    #[test]
+    fn filters_prs_by_base_branch() {
+        let parse = |json: serde_json::Value| -> Event { serde_json::from_value(json).unwrap() };
+        let user = serde_json::json!({ "login": "me" });
+        let pr = |number: u64, base: &str| {
+            parse(serde_json::json!({
+                "id": "1",
+                "type": "PullRequestEvent",
+                "repo": { "name": "org/a" },
+                "created_at": "2019-08-06T10:00:00Z",
+                "payload": {
+                    "action": "opened",
+                    "pull_request": {
+                        "number": number,
+                        "html_url": format!("https://github.com/org/a/pull/{}", number),
+                        "title": format!("PR {}", number),
+                        "user": user,
+                        "base": { "ref": base },
+                    },
+                },
+            }))
+        };
+        let comment = |number: u64, kind: &str| {
+            parse(serde_json::json!({
+                "id": "2",
+                "type": "IssueCommentEvent",
+                "repo": { "name": "org/a" },
+                "created_at": "2019-08-06T11:00:00Z",
+                "payload": {
+                    "action": "created",
+                    "issue": {
+                        "number": number,
+                        "html_url": format!("https://github.com/org/a/{}/{}", kind, number),
+                        "title": format!("{} {}", kind, number),
+                        "user": { "login": "other" },
+                    },
+                },
+            }))
+        };
+        let events = [
+            pr(1, "release/1.0"),
+            pr(2, "main"),
+            comment(3, "pull"),
+            comment(4, "issues"),
+            Event {
+                payload: Some(EventPayload::Push(push(&["Add parser"]))),
+                ..event("org/a")
+            },
+        ];
+        let events: Vec<&Event> = events.iter().collect();
+        let opts = Options {
+            issue_comments: true,
+            enrich: true,
+            ..options()
+        };
+
+        let mut entries = convert(&["me"], &opts, &events).unwrap();
+        entries.retain(|e| keep_base_branch("release/*", e));
+        let mut titles: Vec<&str> = entries.iter().map(|e| e.title.as_str()).collect();
+        titles.sort_unstable();
+        // fetch looks up the base of PRs known from comments, an unknown one is kept
+        assert_eq!(
+            titles,
+            vec!["PR 1", "feature: Add parser", "issues 4", "pull 3"]
+        );
+    }
+
+    #[test]
+    fn merge_only_pushes() {
+        assert!(push(&["Merge branch 'master' into feature"]).is_merge_only());
+        assert!(push(&[
+            "Merge remote-tracking branch 'origin/master' into feature",
+            "Merge pull request #1 from user/fix",
+        ])
+        .is_merge_only());
+        assert!(!push(&["Merge branch 'master' into feature", "Fix typo"]).is_merge_only());
+        assert!(!push(&["Merge sort implementation"]).is_merge_only());
+        assert!(!push(&[]).is_merge_only());
+    }
+
+    #[test]
+    fn commits_of_pushes_without_prs() {
+        let pushed = |r#ref: &str, messages: &[&str]| Event {
+            payload: Some(EventPayload::Push(PushPayload {
+                r#ref: String::from(r#ref),
+                ..push(messages)
+            })),
+            ..event("org/a")
+        };
+        let events = [
+            pushed("refs/heads/feature", &["Add parser\n\nDetails", "Fix typo"]),
+            pushed("refs/heads/feature", &["Fix typo", "Handle errors"]),
+            pushed("refs/heads/master", &["Release"]),
+            pushed("refs/heads/other", &["Merge branch 'master' into other"]),
+        ];
+        let events: Vec<&Event> = events.iter().collect();
+
+        // the lookup didn't run, pushes may belong to PRs
+        assert!(convert(&["me"], &options(), &events).unwrap().is_empty());
+
+        let opts = Options {
+            enrich: true,
+            ..options()
+        };
+        let entries = convert(&["me"], &opts, &events).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].r#type, "Commit");
+        assert_eq!(entries[0].title, "feature: Add parser (3 commits)");
+        assert_eq!(
+            entries[0].url.as_deref(),
+            Some("https://github.com/org/a/commits/feature")
+        );
+        assert_eq!(entries[0].actions, vec!["pushed"]);
+    }
+
+    fn review(login: &str, state: &str) -> Review {
+        Review {
+            user: Some(User {
+                login: String::from(login),
+            }),
+            state: String::from(state),
+        }
+    }
+
+    #[test]
+    fn counts_latest_review_decisions() {
+        let counts = count_reviews(&[
+            review("alice", "CHANGES_REQUESTED"),
+            review("alice", "APPROVED"),
+            review("alice", "COMMENTED"),
+            review("bob", "APPROVED"),
+            review("carol", "CHANGES_REQUESTED"),
+            review("dave", "APPROVED"),
+            review("dave", "DISMISSED"),
+            review("erin", "COMMENTED"),
+        ]);
+
+        assert_eq!(counts.approvals, 2);
+        assert_eq!(counts.changes_requested, 1);
+    }
+
+    #[test]
+    fn delays_pages_when_rate_limit_is_low() {
+        let now = Utc.ymd(2019, 8, 7).and_hms(12, 0, 0);
+        let limit = |remaining| RateLimit {
+            remaining,
+            reset: now + Duration::seconds(60),
+        };
+        assert_eq!(limit(4000).delay(now), None);
+        assert_eq!(
+            limit(59).delay(now),
+            Some(std::time::Duration::from_secs(1))
+        );
+        assert_eq!(limit(0).delay(now), Some(RATE_LIMIT_MAX_DELAY));
+        assert_eq!(limit(10).delay(now + Duration::seconds(61)), None);
+    }
+
+    #[test]
+    fn retries_server_errors_and_rate_limits() {
+        let now = Utc.ymd(2019, 8, 7).and_hms(12, 0, 0);
+        let headers = |pairs: &[(&'static str, String)]| {
+            let mut h = HeaderMap::new();
+            for (name, value) in pairs {
+                h.insert(*name, value.parse().unwrap());
+            }
+            h
+        };
+        let secs = std::time::Duration::from_secs;
+        let none = HeaderMap::new();
+
+        assert_eq!(retry_delay(StatusCode::OK, &none, 0, now), None);
+        assert_eq!(retry_delay(StatusCode::NOT_FOUND, &none, 0, now), None);
+        assert_eq!(
+            retry_delay(StatusCode::BAD_GATEWAY, &none, 0, now),
+            Some(secs(1))
+        );
+        assert_eq!(
+            retry_delay(StatusCode::BAD_GATEWAY, &none, 2, now),
+            Some(secs(4))
+        );
+        // no access, not a rate limit
+        assert_eq!(retry_delay(StatusCode::FORBIDDEN, &none, 0, now), None);
+
+        let retry_after = headers(&[("retry-after", String::from("30"))]);
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &retry_after, 0, now),
+            Some(secs(30))
+        );
+        let reset = |secs_left: i64| {
+            headers(&[
+                ("x-ratelimit-remaining", String::from("0")),
+                (
+                    "x-ratelimit-reset",
+                    (now + Duration::seconds(secs_left)).timestamp().to_string(),
+                ),
+            ])
+        };
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &reset(20), 0, now),
+            Some(secs(20))
+        );
+        assert_eq!(
+            retry_delay(StatusCode::FORBIDDEN, &reset(3600), 0, now),
+            None
+        );
+    }
+
+    #[test]
+    fn enterprise_api_urls() {
+        assert_eq!(api_url(None), "https://api.github.com");
+        assert_eq!(
+            api_url(Some("https://ghe.corp/api/v3/")),
+            "https://ghe.corp/api/v3"
+        );
+        assert_eq!(graphql_url(API_URL), "https://api.github.com/graphql");
+        assert_eq!(
+            graphql_url("https://ghe.corp/api/v3"),
+            "https://ghe.corp/api/graphql"
+        );
+        assert_eq!(web_url(API_URL), "https://github.com");
+        assert_eq!(web_url("https://ghe.corp/api/v3"), "https://ghe.corp");
+    }
+
+    #[test]
+    fn finds_closed_issues() {
+        let body = "Fixes #12, closes: #7\n\nResolved #12. See #3, fix #abc, closing #4";
+        assert_eq!(closed_issues(body), vec![12, 7]);
+        assert!(closed_issues("Nothing to see").is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+// HTTP clients for GitHub, Google and webhooks. A proxy is taken from the config,
+// or from HTTPS_PROXY/HTTP_PROXY environment variables otherwise.
+// Every request identifies the tool with User-Agent as GitHub asks for.
+
+use std::env;
+use std::io::Read;
+use std::sync::Mutex;
+
+use oauth2::{HttpRequest, HttpResponse};
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{Client, Proxy, RedirectPolicy};
+
+use crate::error::StandupError;
+
+struct Settings {
+    proxy: Option<String>,
+    user_agent: Option<String>,
+}
+
+static SETTINGS: Mutex<Settings> = Mutex::new(Settings {
+    proxy: None,
+    user_agent: None,
+});
+
+const DEFAULT_USER_AGENT: &str = concat!("standup-rs/", env!("CARGO_PKG_VERSION"));
+
+pub fn configure(proxy: Option<String>, user_agent: Option<String>) {
+    *SETTINGS.lock().unwrap() = Settings { proxy, user_agent };
+}
+
+fn env_proxy(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|v| !v.is_empty())
+}
+
+fn builder() -> Result<reqwest::ClientBuilder, StandupError> {
+    let settings = SETTINGS.lock().unwrap();
+    let user_agent = settings.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(user_agent).map_err(|e| {
+            StandupError::Invalid(format!("invalid user agent '{}': {}", user_agent, e))
+        })?,
+    );
+    let mut builder = Client::builder().default_headers(headers);
+
+    let invalid = |e: reqwest::Error| StandupError::Invalid(format!("invalid proxy: {}", e));
+    if let Some(url) = &settings.proxy {
+        return Ok(builder.proxy(Proxy::all(url.as_str()).map_err(invalid)?));
+    }
+    if let Some(url) = env_proxy(&["HTTPS_PROXY", "https_proxy"]) {
+        builder = builder.proxy(Proxy::https(url.as_str()).map_err(invalid)?);
+    }
+    if let Some(url) = env_proxy(&["HTTP_PROXY", "http_proxy"]) {
+        builder = builder.proxy(Proxy::http(url.as_str()).map_err(invalid)?);
+    }
+    Ok(builder)
+}
+
+pub fn client() -> Result<Client, StandupError> {
+    Ok(builder()?.build()?)
+}
+
+// same as oauth2::reqwest::http_client but with the proxy
+pub fn oauth_client(request: HttpRequest) -> Result<HttpResponse, oauth2::reqwest::Error> {
+    let client = builder()
+        .map_err(|e| oauth2::reqwest::Error::Other(e.to_string()))?
+        .redirect(RedirectPolicy::none())
+        .build()
+        .map_err(oauth2::reqwest::Error::Reqwest)?;
+    let mut request_builder = client
+        .request(request.method, request.url.as_str())
+        .body(request.body);
+    for (name, value) in &request.headers {
+        request_builder = request_builder.header(name, value);
+    }
+    let mut response = request_builder
+        .send()
+        .map_err(oauth2::reqwest::Error::Reqwest)?;
+
+    let mut body = Vec::new();
+    response
+        .read_to_end(&mut body)
+        .map_err(oauth2::reqwest::Error::Io)?;
+    Ok(HttpResponse {
+        status_code: response.status(),
+        headers: response.headers().clone(),
+        body,
+    })
+}
@@ -1,77 +1,507 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::error::Error;
-use std::io::{self, stderr, BufRead, Write};
-use std::path::Path;
-use std::process;
+use std::fs;
+use std::io::{self, stderr, BufRead, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
 
 use chrono::prelude::*;
 use dirs::home_dir;
+use log::{debug, info, warn};
+use reqwest::header::CONTENT_TYPE;
 use structopt::StructOpt;
 use time::Duration;
 
+mod cache;
+mod clock;
 mod config;
+mod error;
 mod gcalendar;
 mod github;
+mod http;
+mod redact;
 mod report;
 
+use self::clock::{Clock, SystemClock};
 use self::config::Config;
+use self::error::StandupError;
+use self::report::{Format, SortRepos, Theme};
 
 // Cli
+#[derive(StructOpt)]
+enum Cmd {
+    #[structopt(name = "config")]
+    /// Back up and restore the configuration
+    Config(ConfigCmd),
+    #[structopt(name = "doctor")]
+    /// Check the configuration, tokens and network access
+    Doctor,
+}
+
+#[derive(StructOpt)]
+enum ConfigCmd {
+    #[structopt(name = "export")]
+    /// Print the configuration, secrets are redacted by default
+    Export {
+        #[structopt(long = "with-secrets")]
+        /// Include tokens and client secret as is
+        with_secrets: bool,
+    },
+    #[structopt(name = "import")]
+    /// Replace the configuration with an exported one
+    Import {
+        #[structopt(parse(from_os_str))]
+        /// File to read the configuration from, stdin by default
+        file: Option<PathBuf>,
+    },
+}
+
 #[derive(StructOpt)]
 #[structopt(
     name = "standup-rs",
     about = "Generate a report for morning standup using GitHub and Google Calendar."
 )]
 struct Opt {
-    #[structopt(
-        short = "s",
-        long,
-        default_value = "yesterday",
-        parse(try_from_str = parse_since)
-    )]
-    /// Valid values: yesterday, friday, today, yyyy-mm-dd
-    since: DateTime<Utc>,
+    #[structopt(short = "s", long, default_value = "yesterday")]
+    /// Valid values: yesterday, today, monday..sunday, last-week, Nd or Nw (days or weeks ago),
+    /// sprint, start-of-month, end-of-month, start-of-year, yyyy-mm-dd, @unix-timestamp
+    since: String,
+
+    #[structopt(long, conflicts_with = "since")]
+    /// Report the latest N GitHub activities regardless of date, meetings are skipped
+    last: Option<usize>,
 
     #[structopt(short = "u", long, parse(try_from_str = parse_until))]
-    /// Valid values: today, yyyy-mm-dd
+    /// Valid values: today, start-of-month, end-of-month, start-of-year, yyyy-mm-dd, @unix-timestamp
     until: Option<DateTime<Utc>>,
 
+    #[structopt(long = "until-now", conflicts_with = "until")]
+    /// End the period at the current time
+    until_now: bool,
+
+    #[structopt(
+        long = "range",
+        number_of_values = 1,
+        conflicts_with_all = &["since", "until", "until-now", "last"]
+    )]
+    /// Report several periods instead of one, e.g. --range 2019-07-01..2019-07-05 --range 2019-07-29..
+    range: Vec<String>,
+
+    #[structopt(
+        long = "since-buffer",
+        default_value = "0m",
+        parse(try_from_str = parse_duration)
+    )]
+    /// Widen the GitHub events window on both ends, e.g. 30m or 1h
+    since_buffer: Duration,
+
+    #[structopt(long, use_delimiter = true)]
+    /// Comma-separated GitHub logins to report on instead of yourself
+    team: Vec<String>,
+
+    #[structopt(long, default_value = "4")]
+    /// How many --team members are fetched at the same time
+    concurrency: usize,
+
+    #[structopt(long = "max-retries", default_value = "3")]
+    /// Retries of GitHub requests failing with 5xx or a rate limit, with a growing pause between them
+    max_retries: u32,
+
+    #[structopt(long = "no-cache")]
+    /// Neither use nor update the cache of GitHub responses
+    no_cache: bool,
+
+    #[structopt(long = "clear-cache")]
+    /// Remove cached GitHub responses and exit
+    clear_cache: bool,
+
     #[structopt(long = "issue-comments")]
     /// Add issues with comments into a report
     issue_comments: bool,
+
+    #[structopt(long = "work-hours-only")]
+    /// Drop activity outside of work_hours set in the config
+    work_hours_only: bool,
+
+    #[structopt(long = "min-changes")]
+    /// Keep only PRs with more added and deleted lines, costs a request to GitHub per PR
+    min_changes: Option<u64>,
+
+    #[structopt(long, number_of_values = 1)]
+    /// Report only this repository, owner/name or just name, can be repeated
+    repo: Vec<String>,
+
+    #[structopt(long = "exclude-repo", number_of_values = 1)]
+    /// Hide this repository, matched like --repo, can be repeated
+    exclude_repo: Vec<String>,
+
+    #[structopt(long)]
+    /// Keep only PRs and issues with the label
+    label: Option<String>,
+
+    #[structopt(long = "github-base-branch-filter")]
+    /// Keep only PRs targeting matching branches, e.g. 'release/*', other entries are kept
+    github_base_branch_filter: Option<String>,
+
+    #[structopt(
+        short = "f",
+        long,
+        default_value = "text",
+        possible_values = Format::VARIANTS
+    )]
+    /// Output format
+    format: Format,
+
+    #[structopt(
+        long = "sort-repos",
+        default_value = "alpha",
+        possible_values = SortRepos::VARIANTS
+    )]
+    /// Order of repositories: alphabetical or the most active first
+    sort_repos: SortRepos,
+
+    #[structopt(
+        long = "theme",
+        default_value = "plain",
+        possible_values = Theme::VARIANTS
+    )]
+    /// Decorations of the text report: bullets, indentation and separators
+    theme: Theme,
+
+    #[structopt(long = "digest")]
+    /// Group entries by day and then by repository, e.g. for a weekly summary
+    digest: bool,
+
+    #[structopt(long = "flat-single")]
+    /// Put repositories with a single entry on one line in text output
+    flat_single: bool,
+
+    #[structopt(short = "q", long = "quiet")]
+    /// Don't print "(no meetings)" when the calendar has no events
+    quiet: bool,
+
+    #[structopt(long = "show-authors")]
+    /// Add "by @author" to PRs and issues authored by someone else (text and email formats)
+    show_authors: bool,
+
+    #[structopt(long = "relative-time")]
+    /// Add how long ago each activity happened in text output
+    relative_time: bool,
+
+    #[structopt(long = "link-jira")]
+    /// Jira base url to link issue keys like PROJ-123 in titles to
+    link_jira: Option<String>,
+
+    #[structopt(long)]
+    /// Fail if some events are unavailable or can't be parsed
+    strict: bool,
+
+    #[structopt(long = "no-enrich")]
+    /// Don't look up pull requests for pushes, saves requests to GitHub
+    no_enrich: bool,
+
+    #[structopt(long = "with-body")]
+    /// Add the first line of PRs and issues descriptions
+    with_body: bool,
+
+    #[structopt(long)]
+    /// Add a section with your open PRs and open issues assigned to you
+    todo: bool,
+
+    #[structopt(long = "include-discussions")]
+    /// Add Github Discussions opened or commented within the window
+    include_discussions: bool,
+
+    #[structopt(long = "include-wiki")]
+    /// Add wiki pages created or edited
+    include_wiki: bool,
+
+    #[structopt(long = "include-own-reviews")]
+    /// Report reviews and review comments on own PRs as self-reviewed
+    include_own_reviews: bool,
+
+    #[structopt(long = "with-closed-issues")]
+    /// List issues closed by PRs with keywords like "Fixes #123" (text and email formats)
+    with_closed_issues: bool,
+
+    #[structopt(long = "with-sha")]
+    /// Add the short merge commit sha to merged PRs, e.g. @a1b2c3d
+    with_sha: bool,
+
+    #[structopt(long = "with-comment-counts")]
+    /// Add numbers of comments to PRs and issues, as known from the latest event
+    with_comment_counts: bool,
+
+    #[structopt(long = "with-approvals")]
+    /// Add approval counts to your PRs, costs a request to GitHub per PR
+    with_approvals: bool,
+
+    #[structopt(long = "merge-forks")]
+    /// Report activity in forks under the upstream repository, costs a request per repository
+    merge_forks: bool,
+
+    #[structopt(long = "skip-archived")]
+    /// Drop activity in archived repositories
+    skip_archived: bool,
+
+    #[structopt(long = "include-projects")]
+    /// Add items of your GitHub Projects updated within the period
+    include_projects: bool,
+
+    #[structopt(long = "include-private")]
+    /// Fail unless the token can see your private events: it must belong to you and have the repo scope
+    include_private: bool,
+
+    #[structopt(long)]
+    /// Continue an interrupted GitHub fetch with the same parameters
+    resume: bool,
+
+    #[structopt(long)]
+    /// Show only entries that weren't in the previous report
+    diff: bool,
+
+    #[structopt(long)]
+    /// Ask for a note to each PR and issue, notes are saved and shown until the item is gone
+    interactive: bool,
+
+    #[structopt(long)]
+    /// Replace repository names, titles and urls with placeholders
+    anonymize: bool,
+
+    #[structopt(long)]
+    /// Google Calendar id to use instead of the configured one
+    calendar: Option<String>,
+
+    #[structopt(long = "include-declined")]
+    /// Keep calendar events you declined
+    include_declined: bool,
+
+    #[structopt(
+        long = "github-token",
+        env = "STANDUP_GITHUB_TOKEN",
+        hide_env_values = true
+    )]
+    /// GitHub token to use instead of the configured one
+    github_token: Option<String>,
+
+    #[structopt(long = "github-token-scopes-check")]
+    /// Print scopes granted to the GitHub token and exit
+    github_token_scopes_check: bool,
+
+    #[structopt(long = "github-token-file", parse(from_os_str))]
+    /// File to read the GitHub token from
+    github_token_file: Option<PathBuf>,
+
+    #[structopt(long = "output-dir", parse(from_os_str))]
+    /// Save the report into the directory as standup-yyyy-mm-dd file
+    output_dir: Option<PathBuf>,
+
+    #[structopt(long = "no-wizard")]
+    /// Fail instead of running the interactive setup when the config is missing or corrupt
+    no_wizard: bool,
+
+    #[structopt(long)]
+    /// Overwrite an existing report in the output directory
+    force: bool,
+
+    #[structopt(
+        long = "teams-webhook",
+        env = "STANDUP_TEAMS_WEBHOOK",
+        hide_env_values = true
+    )]
+    /// Microsoft Teams incoming webhook to post the report to, requires --format teams
+    teams_webhook: Option<String>,
+
+    #[structopt(subcommand)]
+    cmd: Option<Cmd>,
+
+    #[structopt(short = "y", long = "yes")]
+    /// Post to the webhook without showing the report and asking for confirmation
+    yes: bool,
+
+    #[structopt(long = "pipe-to")]
+    /// Shell command to feed the report to (e.g. pbcopy) instead of printing it
+    pipe_to: Option<String>,
 }
 
-fn parse_date(v: &str) -> Result<Date<Local>, &str> {
+fn parse_date(v: &str) -> Result<Date<Local>, &'static str> {
     NaiveDate::parse_from_str(v, "%Y-%m-%d")
         .map(|v| Local.from_local_date(&v).earliest().unwrap())
         .map_err(|_| "unsupported value")
 }
 
-fn parse_since(v: &str) -> Result<DateTime<Utc>, &str> {
+// exact instant as @-prefixed unix timestamp in seconds, e.g. @1565049600
+fn parse_timestamp(v: &str) -> Result<DateTime<Utc>, &'static str> {
+    v.parse::<i64>()
+        .ok()
+        .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+        .ok_or("unsupported value")
+}
+
+// sprint keyword is resolved using the config, that's why since isn't parsed by structopt
+fn parse_since(
+    v: &str,
+    sprint: Option<&config::Sprint>,
+    clock: &dyn Clock,
+) -> Result<DateTime<Utc>, &'static str> {
+    let today = clock.today();
+    if let Some(ts) = v.strip_prefix('@') {
+        return parse_timestamp(ts);
+    }
+
     let d = match v {
-        "yesterday" => Local::today() - Duration::days(1),
-        "friday" => {
-            let mut r = Local::today();
-            while r.weekday() != Weekday::Fri {
-                r = r - Duration::days(1);
-            }
-            r
+        "yesterday" => today - Duration::days(1),
+        "today" => today,
+        // monday of the previous week
+        "last-week" => {
+            today
+                - Duration::days(i64::from(today.weekday().num_days_from_monday()))
+                - Duration::weeks(1)
         }
-        "today" => Local::today(),
-        _ => parse_date(v)?,
+        "sprint" => sprint_start(sprint.ok_or("sprint isn't configured")?, today)?,
+        _ => match (v.parse::<Weekday>(), days_ago(v)) {
+            (Ok(weekday), _) => last_weekday(today, weekday),
+            (_, Some(days)) => today
+                .checked_sub_signed(Duration::days(days))
+                .ok_or("unsupported value")?,
+            _ => period_boundary(v, today).map_or_else(|| parse_date(v), Ok)?,
+        },
     };
 
     Ok(DateTime::from(d.and_hms(0, 0, 0)))
 }
 
+// today if it is that weekday already
+fn last_weekday(today: Date<Local>, weekday: Weekday) -> Date<Local> {
+    let mut r = today;
+    while r.weekday() != weekday {
+        r = r - Duration::days(1);
+    }
+    r
+}
+
+// 3d is three days ago, 2w is two weeks ago
+fn days_ago(v: &str) -> Option<i64> {
+    let (n, days) = match (v.strip_suffix('d'), v.strip_suffix('w')) {
+        (Some(n), _) => (n, 1),
+        (_, Some(n)) => (n, 7),
+        _ => return None,
+    };
+    n.parse::<u32>().ok().map(|n| i64::from(n) * days)
+}
+
+// until is exclusive, so end-of-month is the first day of the next month
+fn period_boundary(v: &str, today: Date<Local>) -> Option<Date<Local>> {
+    let d = match v {
+        "start-of-month" => today.with_day(1)?,
+        "end-of-month" => {
+            let (year, month) = match today.month() {
+                12 => (today.year() + 1, 1),
+                m => (today.year(), m + 1),
+            };
+            Local.ymd_opt(year, month, 1).earliest()?
+        }
+        "start-of-year" => today.with_ordinal(1)?,
+        _ => return None,
+    };
+    Some(d)
+}
+
+// Sprints follow each other without gaps starting from the configured date.
+// The first day of a sprint belongs to the new sprint only,
+// so on that day `sprint` resolves to today.
+fn sprint_start(sprint: &config::Sprint, today: Date<Local>) -> Result<Date<Local>, &'static str> {
+    if sprint.length_days == 0 {
+        return Err("sprint length must be positive");
+    }
+
+    let length = i64::from(sprint.length_days);
+    let days = (today.naive_local() - sprint.start).num_days();
+    let start = sprint.start + Duration::days(days.div_euclid(length) * length);
+    Ok(Local.from_local_date(&start).earliest().unwrap())
+}
+
 fn parse_until(v: &str) -> Result<DateTime<Utc>, &str> {
+    parse_until_at(v, &SystemClock)
+}
+
+fn parse_until_at<'a>(v: &'a str, clock: &dyn Clock) -> Result<DateTime<Utc>, &'a str> {
+    let today = clock.today();
+    if let Some(ts) = v.strip_prefix('@') {
+        return parse_timestamp(ts);
+    }
+
     let d = match v {
-        "today" => Local::today(),
-        _ => parse_date(v)?,
+        "today" => today,
+        _ => period_boundary(v, today).map_or_else(|| parse_date(v), Ok)?,
     };
 
     Ok(DateTime::from(d.and_hms(0, 0, 0)))
 }
 
+// since..until with the same values as --since and --until, until may be omitted
+fn parse_range(
+    v: &str,
+    sprint: Option<&config::Sprint>,
+    clock: &dyn Clock,
+) -> Result<(DateTime<Utc>, Option<DateTime<Utc>>), String> {
+    let (since, until) = v
+        .split_once("..")
+        .ok_or_else(|| format!("invalid value for --range '{}': expected since..until", v))?;
+    let since = parse_since(since, sprint, clock)
+        .map_err(|e| format!("invalid since in --range '{}': {}", v, e))?;
+    let until = match until {
+        "" => None,
+        until => Some(
+            parse_until_at(until, clock)
+                .map_err(|e| format!("invalid until in --range '{}': {}", v, e))?,
+        ),
+    };
+    if until.is_some_and(|u| u <= since) {
+        return Err(format!(
+            "invalid value for --range '{}': until is not after since",
+            v
+        ));
+    }
+    Ok((since, until))
+}
+
+// sorted by since, overlapping windows are joined so nothing is fetched twice
+fn join_windows(
+    mut windows: Vec<(DateTime<Utc>, Option<DateTime<Utc>>)>,
+) -> Vec<(DateTime<Utc>, Option<DateTime<Utc>>)> {
+    windows.sort_by_key(|w| w.0);
+    let mut res: Vec<(DateTime<Utc>, Option<DateTime<Utc>>)> = Vec::new();
+    for (since, until) in windows {
+        match res.last_mut() {
+            Some(last) if last.1.is_none_or(|u| since <= u) => {
+                last.1 = match (last.1, until) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+            }
+            _ => res.push((since, until)),
+        }
+    }
+    res
+}
+
+fn parse_duration(v: &str) -> Result<Duration, &str> {
+    let (n, unit): (&str, fn(i64) -> Duration) = if let Some(n) = v.strip_suffix('m') {
+        (n, Duration::minutes)
+    } else if let Some(n) = v.strip_suffix('h') {
+        (n, Duration::hours)
+    } else {
+        return Err("unsupported value");
+    };
+
+    n.parse::<u32>()
+        .map(|n| unit(i64::from(n)))
+        .map_err(|_| "unsupported value")
+}
+
 fn ask(question: &str) -> String {
     let mut answer = String::new();
 
@@ -96,15 +526,32 @@ fn ask(question: &str) -> String {
 const YES_ANSWERS: [&str; 3] = ["y", "yes", "yep"];
 const NO_ANSWERS: [&str; 3] = ["n", "no", "nope"];
 
+// unlike ask, an empty answer is accepted
+fn ask_optional(question: &str) -> String {
+    let mut answer = String::new();
+    print!("{}: ", question);
+    io::stdout().flush().unwrap();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .expect("couldn't read from stdio");
+    answer.trim().to_owned()
+}
+
+// end of input is taken as no, e.g. when stdin isn't a terminal
 fn ask_yes_no(question: &str) -> bool {
     let mut answer = String::new();
     loop {
         print!("{} (Y/N): ", question);
         io::stdout().flush().unwrap();
-        io::stdin()
+        let read = io::stdin()
             .lock()
             .read_line(&mut answer)
             .expect("couldn't read from stdio");
+        if read == 0 {
+            println!();
+            return false;
+        }
 
         answer = answer.trim().to_lowercase();
         if YES_ANSWERS.iter().any(|x| x == &answer) {
@@ -116,10 +563,30 @@ fn ask_yes_no(question: &str) -> bool {
     }
 }
 
+fn authorize_calendar(cfg: &mut Config) {
+    let c = gcalendar::Calendar::new(cfg);
+    println!("Please visit the url to authorize the application");
+    println!("{}", c.authorize_url());
+    cfg.google_token = Some(c.listen_for_code());
+    register_secrets(cfg);
+}
+
 fn wizard() -> Result<Config, String> {
     println!("Standup-rs requires access tokens to generate reports.");
+    let api_url = if ask_yes_no("Are you on GitHub Enterprise Server?") {
+        let url = ask("Enter the API base url, e.g. https://github.example.com/api/v3");
+        Some(github::api_url(Some(&url)))
+    } else {
+        None
+    };
     let github_username = ask("Enter your github username");
-    println!("Go to https://github.com/settings/tokens to obtain personal access token.");
+    if api_url.is_some() {
+        println!(
+            "Go to Settings > Developer settings on your server to obtain personal access token."
+        );
+    } else {
+        println!("Go to https://github.com/settings/tokens to obtain personal access token.");
+    }
     let github_token = ask("Enter github token");
 
     // TODO validate the token & username here
@@ -128,10 +595,22 @@ fn wizard() -> Result<Config, String> {
         github: config::Github {
             username: github_username,
             token: github_token,
+            aliases: Vec::new(),
+            include_projects: false,
+            extra_tokens: Vec::new(),
+            deploy_branches: Vec::new(),
+            api_url,
         },
         google_client: None,
         google_token: None,
         gcal: None,
+        sprint: None,
+        action_priority: Vec::new(),
+        notes: HashMap::new(),
+        repo_aliases: HashMap::new(),
+        proxy: None,
+        user_agent: None,
+        work_hours: None,
     };
 
     if ask_yes_no("Do you want to connect Google Calendar?") {
@@ -156,13 +635,10 @@ fn wizard() -> Result<Config, String> {
 
         // run auth & choose calendar id flow
 
-        let c = gcalendar::Calendar::new(&cfg);
-        println!("Please visit the url to authorize the application");
-        println!("{}", c.authorize_url());
-        cfg.google_token = Some(c.listen_for_code());
+        authorize_calendar(&mut cfg);
 
         let c = gcalendar::Calendar::new(&cfg);
-        let calendars = c.list()?;
+        let calendars = c.list().map_err(|e| e.to_string())?;
         println!("Available calendars:");
         for (i, cal) in calendars.iter().enumerate() {
             println!("[{}]: {}", i + 1, cal.summary)
@@ -184,62 +660,1018 @@ fn wizard() -> Result<Config, String> {
     Ok(cfg)
 }
 
+// directory of the config and progress files:
+// $STANDUP_DIR > home directory > current directory
+fn standup_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = env::var_os("STANDUP_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = home_dir() {
+        return Ok(dir);
+    }
+
+    let dir = env::current_dir().map_err(|e| {
+        format!(
+            "can not determine home directory nor current directory ({}), set STANDUP_DIR",
+            e
+        )
+    })?;
+    warn!(
+        "Can not determine home directory, using {}. Set STANDUP_DIR to choose another one",
+        dir.display()
+    );
+    Ok(dir)
+}
+
 fn run() -> Result<(), Box<dyn Error>> {
     let opt = Opt::from_args();
-    let config_path = Path::join(&home_dir().unwrap(), ".standup");
-    let mut cfg = match Config::load(&config_path)? {
-        Some(c) => c,
-        None => {
+    if let Some(url) = &opt.teams_webhook {
+        if !matches!(opt.format, Format::Teams) {
+            return Err("--teams-webhook requires --format teams".into());
+        }
+        // a card holds a single report, headings of users and days would break the json
+        if !opt.team.is_empty() || opt.digest {
+            return Err("--teams-webhook can't be combined with --team or --digest".into());
+        }
+        // anyone with the url can post to the channel
+        redact::register(url);
+    }
+    let dir = standup_dir()?;
+    let config_path = Path::join(&dir, ".standup");
+    let cache_dir = Path::join(&dir, ".standup-cache");
+    if opt.clear_cache {
+        cache::Cache::clear(&cache_dir)?;
+        println!(
+            "Removed cached GitHub responses from {}",
+            cache_dir.display()
+        );
+        return Ok(());
+    }
+    match &opt.cmd {
+        Some(Cmd::Config(cmd)) => return config_command(cmd, &config_path),
+        Some(Cmd::Doctor) => return doctor(&opt, &config_path),
+        None => {}
+    }
+    debug!("loading config from {}", config_path.display());
+    let mut cfg = match Config::load(&config_path) {
+        Ok(Some(c)) => c,
+        Ok(None) if opt.no_wizard => {
+            return Err(format!(
+                "config file {} not found, run without --no-wizard once to set it up \
+                 or restore a saved one with `config import`",
+                config_path.display()
+            )
+            .into())
+        }
+        Ok(None) => {
+            let c = wizard()?;
+            c.save(&config_path)?;
+            c
+        }
+        Err(StandupError::Parse(e)) => {
+            let msg = format!(
+                "config file {} is corrupt ({}), fix or delete it to run the setup again",
+                config_path.display(),
+                e
+            );
+            if opt.no_wizard
+                || !ask_yes_no(&format!("{}\nBack it up and run the setup again?", msg))
+            {
+                return Err(msg.into());
+            }
+            let backup = Config::backup(&config_path)?;
+            println!("Corrupt config is saved to {}", backup.display());
             let c = wizard()?;
             c.save(&config_path)?;
             c
         }
+        Err(e) => return Err(e.into()),
     };
 
-    if cfg.gcal.is_some() {
-        // FIXME I have to re-create client after checking for new token
-        // because I can't mutate an object that is already borrowed (it may cause race condition)
-        // can it be solved with different life-time for cfg inside calendar?
-        // or do I need to refactor it somehow?
-        {
-            let c = gcalendar::Calendar::new(&cfg);
-            let new_token = c.refresh_if_needed()?;
-            if new_token.is_some() {
-                cfg.google_token = new_token;
+    register_secrets(&cfg);
+    http::configure(cfg.proxy.clone(), cfg.user_agent.clone());
+
+    if opt.github_token_scopes_check {
+        return check_token_scopes(
+            &cfg.github.username,
+            &resolve_github_token(&opt, &cfg)?,
+            &github::api_url(cfg.github.api_url.as_deref()),
+        );
+    }
+
+    let clock = SystemClock;
+    let now = clock.now().with_timezone(&Utc);
+    // --last takes precedence over the default --since, the window starts at the oldest fetched event
+    let since = match opt.last {
+        Some(_) => Utc.timestamp_opt(0, 0).unwrap(),
+        None => parse_since(&opt.since, cfg.sprint.as_ref(), &clock)
+            .map_err(|e| format!("invalid value for --since '{}': {}", opt.since, e))?,
+    };
+
+    // a single window unless --range is given
+    let windows = match opt.range.as_slice() {
+        [] => vec![(since, opt.until.or(opt.until_now.then_some(now)))],
+        ranges => join_windows(
+            ranges
+                .iter()
+                .map(|r| parse_range(r, cfg.sprint.as_ref(), &clock))
+                .collect::<Result<_, _>>()?,
+        ),
+    };
+    let since = windows[0].0;
+    let events = |c: &gcalendar::Calendar, calendar_id: &str| {
+        windows
+            .iter()
+            .map(|(since, until)| {
+                c.events(
+                    calendar_id,
+                    *since,
+                    until.unwrap_or(now),
+                    opt.include_declined,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|m| m.concat())
+    };
+
+    let mut meetings = Vec::new();
+    let mut empty_calendar = false;
+    let calendar_id = opt
+        .calendar
+        .clone()
+        .or_else(|| cfg.gcal.as_ref().map(|c| c.id.clone()));
+    if opt.calendar.is_some() && cfg.google_client.is_none() {
+        return Err("--calendar requires Google Calendar to be connected".into());
+    }
+
+    // meetings are only known for the own report
+    if let Some(calendar_id) = calendar_id.filter(|_| opt.last.is_none() && opt.team.is_empty()) {
+        // the token was removed from the config to authorize again
+        if cfg.google_token.is_none() {
+            authorize_calendar(&mut cfg);
+            cfg.save(&config_path)?;
+            info!("saved new Google token to {}", config_path.display());
+        }
+        let c = gcalendar::Calendar::new(&cfg);
+        c.refresh_if_needed()?;
+        let result = events(&c, &calendar_id);
+        if let Some(token) = c.refreshed_token() {
+            cfg.google_token = Some(token);
+            register_secrets(&cfg);
+            cfg.save(&config_path)?;
+            info!("saved refreshed Google token to {}", config_path.display());
+        }
+        meetings = match result {
+            Err(StandupError::Auth(ref e))
+                if e == gcalendar::INSUFFICIENT_SCOPE
+                    && ask_yes_no(
+                        "Google Calendar token is missing required permissions. Re-authorize now?",
+                    ) =>
+            {
+                authorize_calendar(&mut cfg);
                 cfg.save(&config_path)?;
+                info!("saved new Google token to {}", config_path.display());
+                events(&gcalendar::Calendar::new(&cfg), &calendar_id)?
             }
+            r => r?,
         };
-        let c = gcalendar::Calendar::new(&cfg);
-        let events = c.events(opt.since, opt.until)?;
-        for e in events {
-            println!("* {}", e);
+        empty_calendar = meetings.is_empty();
+    }
+
+    let work_hours = match &cfg.work_hours {
+        Some(hours) if opt.work_hours_only => Some((hours.start, hours.end)),
+        None if opt.work_hours_only => {
+            return Err("--work-hours-only requires work_hours in the config".into())
+        }
+        _ => None,
+    };
+
+    let github_token = resolve_github_token(&opt, &cfg)?;
+    redact::register(&github_token);
+    let github_opts = github::Options {
+        aliases: cfg.github.aliases.clone(),
+        issue_comments: opt.issue_comments,
+        since_buffer: opt.since_buffer,
+        progress_file: Path::join(&dir, ".standup-progress"),
+        resume: opt.resume,
+        strict: opt.strict,
+        enrich: !opt.no_enrich,
+        include_projects: opt.include_projects || cfg.github.include_projects,
+        with_body: opt.with_body,
+        with_approvals: opt.with_approvals,
+        with_comment_counts: opt.with_comment_counts,
+        with_sha: opt.with_sha,
+        with_closed_issues: opt.with_closed_issues,
+        own_reviews: opt.include_own_reviews,
+        deploy_branches: cfg.github.deploy_branches.clone(),
+        min_changes: opt.min_changes,
+        include_discussions: opt.include_discussions,
+        include_wiki: opt.include_wiki,
+        extra_tokens: cfg.github.extra_tokens.clone(),
+        action_priority: cfg.action_priority.clone(),
+        skip_archived: opt.skip_archived,
+        merge_forks: opt.merge_forks,
+        label: opt.label.clone(),
+        base_branch: opt.github_base_branch_filter.clone(),
+        include_private: opt.include_private,
+        last: opt.last,
+        repos: opt.repo.clone(),
+        exclude_repos: opt.exclude_repo.clone(),
+        max_retries: opt.max_retries,
+        cache_dir: Some(cache_dir).filter(|_| !opt.no_cache),
+        api_url: github::api_url(cfg.github.api_url.as_deref()),
+    };
+
+    // user is None for the own report
+    let mut fetched = Vec::new();
+    for &(since, until) in windows.iter() {
+        let mut window_fetched = Vec::new();
+        if opt.team.is_empty() {
+            let grouped_events = github::fetch(
+                &cfg.github.username,
+                &github_token,
+                since,
+                until,
+                &github_opts,
+            )?;
+            window_fetched.push((None, grouped_events));
+        } else {
+            let results = github::fetch_team(
+                &opt.team,
+                &github_token,
+                since,
+                until,
+                &github_opts,
+                opt.concurrency,
+            );
+            for (user, result) in results {
+                match result {
+                    Ok(grouped_events) => window_fetched.push((Some(user), grouped_events)),
+                    Err(e) => warn!("Can not fetch activity of {}: {}", user, e),
+                }
+            }
+        }
+        // an entry active in several windows is reported once
+        for (user, grouped_events) in window_fetched {
+            match fetched.iter_mut().find(|(u, _)| *u == user) {
+                Some((_, into)) => report::merge_repos(into, grouped_events),
+                None => fetched.push((user, grouped_events)),
+            }
+        }
+    }
+    if windows.len() > 1 {
+        for (_, grouped_events) in fetched.iter_mut() {
+            for entries in grouped_events.values_mut() {
+                report::sort_entries(entries, &cfg.action_priority);
+            }
         }
     }
 
-    let grouped_events = github::fetch(
-        &cfg.github.username,
-        &cfg.github.token,
-        opt.since,
-        opt.until,
-        opt.issue_comments,
-    )?;
+    let mut render_opts = report::RenderOptions {
+        flat_single: opt.flat_single,
+        relative_time: opt.relative_time,
+        jira_url: opt.link_jira.clone(),
+        theme: opt.theme,
+        show_authors: opt.show_authors,
+        me: Vec::new(),
+        // days of a digest would repeat it
+        empty_calendar: empty_calendar && !opt.quiet && !opt.digest,
+    };
+    let mut output = String::new();
+    let mut date = clock.today();
+    for (i, (user, grouped_events)) in fetched.into_iter().enumerate() {
+        let since = match opt.last {
+            Some(_) => grouped_events
+                .values()
+                .flatten()
+                .filter_map(|e| e.created_at)
+                .min()
+                .unwrap_or(now),
+            None => since,
+        };
+
+        let mut report = report::Report {
+            since,
+            until: windows[windows.len() - 1].1,
+            meetings: std::mem::take(&mut meetings),
+            repos: grouped_events,
+            todo: Vec::new(),
+            sort_repos: opt.sort_repos,
+        };
+        if let Some((start, end)) = work_hours {
+            report.retain_within_hours(start, end);
+        }
+        if opt.todo && user.is_none() {
+            report.todo = github::fetch_todo(
+                &cfg.github.username,
+                &github_token,
+                &github_opts.api_url,
+                opt.max_retries,
+            )?;
+        }
+        // notes belong to the own report only
+        if user.is_none() {
+            annotate(&mut report, &mut cfg.notes, opt.interactive);
+            if opt.interactive {
+                cfg.save(&config_path)?;
+            }
+
+            let snapshot_path = Path::join(&dir, ".standup-snapshot");
+            let seen = load_snapshot(&snapshot_path)?;
+            save_snapshot(&snapshot_path, &report.keys())?;
+            if opt.diff {
+                report.retain_new(&seen);
+            }
+        }
+        report.alias_repos(&cfg.repo_aliases);
+        if opt.anonymize {
+            report.anonymize();
+        }
+        date = report.date();
+
+        render_opts.me = match &user {
+            Some(user) => vec![user.clone()],
+            None => std::iter::once(&cfg.github.username)
+                .chain(&cfg.github.aliases)
+                .cloned()
+                .collect(),
+        };
+        if let Some(user) = user {
+            let user = if opt.anonymize {
+                format!("user {}", i + 1)
+            } else {
+                user
+            };
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&format!("## {}\n\n", user));
+        }
+        if !opt.digest {
+            output.push_str(&report::render(&opt.format, &report, &render_opts));
+            continue;
+        }
+        // days are nested under users in team reports
+        let level = if opt.team.is_empty() { "##" } else { "###" };
+        for (day, report) in report.split_by_day() {
+            if !output.is_empty() && !output.ends_with("\n\n") {
+                output.push('\n');
+            }
+            match day {
+                Some(day) => {
+                    output.push_str(&format!("{} {}\n\n", level, day.format("%A, %Y-%m-%d")))
+                }
+                None => output.push_str(&format!("{} Other\n\n", level)),
+            }
+            output.push_str(&report::render(&opt.format, &report, &render_opts));
+        }
+    }
+
+    if let Some(dir) = &opt.output_dir {
+        save_report(dir, date, &opt.format, opt.force, &output)?;
+    }
+
+    if let Some(url) = &opt.teams_webhook {
+        let confirmed = opt.yes || {
+            print!("{}", output);
+            ask_yes_no("Post this to Teams?")
+        };
+        if confirmed {
+            post_to_teams(url, &output)?;
+        } else {
+            warn!("The report wasn't posted, pass --yes to post without confirmation");
+        }
+    }
+
+    match &opt.pipe_to {
+        Some(command) => pipe_to(command, &output)?,
+        None if opt.output_dir.is_none() && opt.teams_webhook.is_none() => print!("{}", output),
+        None => (),
+    }
+
+    Ok(())
+}
+
+fn check_token_scopes(user: &str, token: &str, api_url: &str) -> Result<(), Box<dyn Error>> {
+    redact::register(token);
+    let scopes = match github::token_scopes(user, token, api_url)? {
+        Some(scopes) => scopes,
+        None => {
+            println!("The token doesn't report scopes, it is probably a fine-grained token.");
+            println!("Make sure it has read access to events, pull requests and issues.");
+            return Ok(());
+        }
+    };
+
+    if scopes.is_empty() {
+        println!("Granted scopes: none");
+    } else {
+        println!("Granted scopes: {}", scopes.join(", "));
+    }
+    println!("Public events feed: ok, no scopes required");
+    for (scope, purpose) in github::TOKEN_SCOPES {
+        let status = if github::scope_granted(&scopes, scope) {
+            "ok"
+        } else {
+            "MISSING"
+        };
+        println!("{}: {}, needed for {}", scope, status, purpose);
+    }
+
+    Ok(())
+}
+
+enum CheckStatus {
+    Ok,
+    // doesn't break reports but limits them
+    Warn,
+    Fail,
+    Skip,
+}
+
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    message: String,
+    hint: &'static str,
+}
+
+impl Check {
+    fn new(name: &'static str, result: Result<String, String>, hint: &'static str) -> Check {
+        let (status, message) = match result {
+            Ok(m) => (CheckStatus::Ok, m),
+            Err(m) => (CheckStatus::Fail, m),
+        };
+        Check {
+            name,
+            status,
+            message,
+            hint,
+        }
+    }
+
+    fn skipped(name: &'static str, message: &str) -> Check {
+        Check {
+            name,
+            status: CheckStatus::Skip,
+            message: message.into(),
+            hint: "",
+        }
+    }
+}
+
+fn reachable(url: &str) -> Result<String, String> {
+    let resp = http::client()
+        .map_err(|e| e.to_string())?
+        .get(url)
+        .send()
+        .map_err(|e| format!("can not reach {}: {}", url, e))?;
+    Ok(format!("{} responded with {}", url, resp.status()))
+}
+
+fn check_github_token(opt: &Opt, cfg: &Config) -> Check {
+    const HINT: &str =
+        "create a token at https://github.com/settings/tokens and set it in the config";
+    let api_url = github::api_url(cfg.github.api_url.as_deref());
+    let scopes = resolve_github_token(opt, cfg).and_then(|token| {
+        github::token_scopes(&cfg.github.username, &token, &api_url).map_err(|e| e.to_string())
+    });
+    let scopes = match scopes {
+        Ok(Some(scopes)) => scopes,
+        Ok(None) => {
+            return Check::new(
+                "GitHub token",
+                Ok("valid, fine-grained tokens don't report scopes".into()),
+                "",
+            )
+        }
+        Err(e) => return Check::new("GitHub token", Err(e), HINT),
+    };
+    let missing: Vec<_> = github::TOKEN_SCOPES
+        .iter()
+        .filter(|(scope, _)| !github::scope_granted(&scopes, scope))
+        .map(|(scope, purpose)| format!("{} ({})", scope, purpose))
+        .collect();
+    if missing.is_empty() {
+        return Check::new("GitHub token", Ok("valid, all scopes granted".into()), "");
+    }
+    Check {
+        name: "GitHub token",
+        status: CheckStatus::Warn,
+        message: format!("valid, missing scopes: {}", missing.join(", ")),
+        hint: "grant the scopes at https://github.com/settings/tokens if you need them",
+    }
+}
+
+// refreshes the token when it has expired and reads the last minute of the calendar
+fn check_google_token(cfg: &mut Config, config_path: &PathBuf) -> Check {
+    const HINT: &str = "remove google_token from the config and run standup-rs to authorize again";
+    if cfg.google_client.is_none() || cfg.google_token.is_none() {
+        return Check::skipped("Google token", "Google Calendar isn't connected");
+    }
+    let c = gcalendar::Calendar::new(cfg);
+    let calendar_id = cfg.gcal.as_ref().map_or("primary", |c| c.id.as_str());
+    let now = Utc::now();
+    let result = c
+        .refresh_if_needed()
+        .and_then(|_| c.events(calendar_id, now - Duration::minutes(1), now, true));
+    if let Some(token) = c.refreshed_token() {
+        cfg.google_token = Some(token);
+        register_secrets(cfg);
+        if let Err(e) = cfg.save(config_path) {
+            return Check::new(
+                "Google token",
+                Err(e.to_string()),
+                "check permissions of the config file",
+            );
+        }
+    }
+    let result = result.map(|_| "valid".into()).map_err(|e| e.to_string());
+    Check::new("Google token", result, HINT)
+}
+
+// the port is only needed while authorizing Google Calendar
+fn check_oauth_port() -> Check {
+    match TcpListener::bind(("127.0.0.1", gcalendar::OAUTH_PORT)) {
+        Ok(_) => Check::new(
+            "OAuth port",
+            Ok(format!("{} is free", gcalendar::OAUTH_PORT)),
+            "",
+        ),
+        Err(e) => Check {
+            name: "OAuth port",
+            status: CheckStatus::Warn,
+            message: format!("can not open {}: {}", gcalendar::OAUTH_PORT, e),
+            hint: "stop the program using the port before authorizing Google Calendar",
+        },
+    }
+}
+
+// runs every check even if some fail, checks that need the config are skipped without it
+fn doctor(opt: &Opt, config_path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let mut checks = Vec::new();
+    let cfg = match Config::load(config_path) {
+        Ok(Some(c)) => {
+            checks.push(Check::new(
+                "config",
+                Ok(format!("{} is valid", config_path.display())),
+                "",
+            ));
+            Some(c)
+        }
+        Ok(None) => {
+            checks.push(Check::new(
+                "config",
+                Err(format!("{} not found", config_path.display())),
+                "run standup-rs to set it up or restore a saved one with `config import`",
+            ));
+            None
+        }
+        Err(e) => {
+            checks.push(Check::new(
+                "config",
+                Err(e.to_string()),
+                "fix the file or restore a saved one with `config import`",
+            ));
+            None
+        }
+    };
+    if let Some(cfg) = &cfg {
+        register_secrets(cfg);
+        http::configure(cfg.proxy.clone(), cfg.user_agent.clone());
+    }
+
+    const NETWORK_HINT: &str =
+        "check the network connection and the proxy, see proxy in the config or HTTPS_PROXY";
+    let api_url = github::api_url(cfg.as_ref().and_then(|c| c.github.api_url.as_deref()));
+    checks.push(Check::new("GitHub API", reachable(&api_url), NETWORK_HINT));
+    checks.push(Check::new(
+        "Google API",
+        reachable("https://www.googleapis.com/discovery/v1/apis"),
+        NETWORK_HINT,
+    ));
+    match cfg {
+        Some(mut cfg) => {
+            checks.push(check_github_token(opt, &cfg));
+            checks.push(check_google_token(&mut cfg, config_path));
+        }
+        None => {
+            checks.push(Check::skipped("GitHub token", "no config"));
+            checks.push(Check::skipped("Google token", "no config"));
+        }
+    }
+    checks.push(check_oauth_port());
+
+    let (mut passed, mut warned, mut failed) = (0, 0, 0);
+    for check in &checks {
+        let status = match check.status {
+            CheckStatus::Ok => {
+                passed += 1;
+                "ok"
+            }
+            CheckStatus::Warn => {
+                warned += 1;
+                "WARN"
+            }
+            CheckStatus::Fail => {
+                failed += 1;
+                "FAIL"
+            }
+            CheckStatus::Skip => "skip",
+        };
+        println!("[{:4}] {}: {}", status, check.name, check.message);
+        if matches!(check.status, CheckStatus::Warn | CheckStatus::Fail) {
+            println!("       {}", check.hint);
+        }
+    }
+    println!("{} passed, {} warnings, {} failed", passed, warned, failed);
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed", failed, checks.len()).into());
+    }
+    Ok(())
+}
 
-    for (repo, events) in grouped_events {
-        println!("* {}:", repo);
-        for e in events {
-            println!("  - {}", e)
+// notes are kept for items present in the report, so with --interactive
+// notes of items that disappeared from the report are dropped
+fn annotate(report: &mut report::Report, notes: &mut HashMap<String, String>, interactive: bool) {
+    if interactive {
+        println!("Enter a note for each item, empty keeps the current one, - removes it");
+        let mut kept = HashMap::new();
+        for e in report
+            .sorted_repos()
+            .into_iter()
+            .flat_map(|(_, entries)| entries)
+        {
+            let url = match &e.url {
+                Some(url) => url,
+                None => continue,
+            };
+            let current = notes.get(url);
+            let question = match current {
+                Some(note) => format!("{} [{}]", e.title, note),
+                None => e.title.clone(),
+            };
+            let note = match ask_optional(&question).as_str() {
+                "" => current.cloned(),
+                "-" => None,
+                answer => Some(String::from(answer)),
+            };
+            if let Some(note) = note {
+                kept.insert(url.clone(), note);
+            }
         }
+        *notes = kept;
+    }
+
+    for e in report.repos.values_mut().flatten() {
+        e.note = e.url.as_ref().and_then(|url| notes.get(url).cloned());
+    }
+}
+
+// keys of entries of the previous report, see --diff
+fn load_snapshot(path: &Path) -> Result<HashSet<String>, String> {
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+    let json = fs::read_to_string(path).map_err(|e| format!("can not read snapshot: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("can not parse snapshot: {}", e))
+}
+
+fn save_snapshot(path: &Path, keys: &[String]) -> Result<(), String> {
+    let json = serde_json::to_string(keys).expect("strings are always serializable");
+    fs::write(path, json).map_err(|e| format!("can not write snapshot: {}", e))
+}
+
+fn config_command(cmd: &ConfigCmd, config_path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    match cmd {
+        ConfigCmd::Export { with_secrets } => {
+            let mut cfg = match Config::load(config_path)? {
+                Some(c) => c,
+                None => return Err(format!("no config at {}", config_path.display()).into()),
+            };
+            if !with_secrets {
+                cfg.redact_secrets();
+            }
+            println!("{}", cfg.to_json()?);
+        }
+        ConfigCmd::Import { file } => {
+            let json = match file {
+                Some(path) => fs::read_to_string(path)
+                    .map_err(|e| format!("can not read {}: {}", path.display(), e))?,
+                None => {
+                    let mut json = String::new();
+                    io::stdin()
+                        .read_to_string(&mut json)
+                        .map_err(|e| format!("can not read stdin: {}", e))?;
+                    json
+                }
+            };
+            let mut cfg: Config =
+                serde_json::from_str(&json).map_err(|e| format!("invalid config: {}", e))?;
+
+            let current = Config::load(config_path).ok().flatten();
+            if let Some(current) = &current {
+                cfg.restore_secrets(current);
+            }
+            // stdin is taken by the config, questions can be asked only when reading a file
+            let interactive = file.is_some();
+            if cfg.has_redacted_secrets() {
+                if !interactive {
+                    return Err(
+                        "the config has redacted secrets, import it from a file to enter them"
+                            .into(),
+                    );
+                }
+                restore_secrets_interactively(&mut cfg);
+            }
+
+            if interactive && current.is_some() && !ask_yes_no("Overwrite the current config?") {
+                return Ok(());
+            }
+            cfg.save(config_path)?;
+            println!("Config is saved to {}", config_path.display());
+        }
+    }
+    Ok(())
+}
+
+fn restore_secrets_interactively(cfg: &mut Config) {
+    if cfg.github.token == config::REDACTED {
+        cfg.github.token = ask("Enter github token");
+    }
+    if cfg
+        .github
+        .extra_tokens
+        .iter()
+        .any(|t| t == config::REDACTED)
+    {
+        println!("Extra GitHub tokens are redacted and dropped, add them to the config again");
+        cfg.github.extra_tokens.retain(|t| t != config::REDACTED);
+    }
+    if let Some(client) = &mut cfg.google_client {
+        if client.client_secret == config::REDACTED {
+            client.client_secret = ask("Enter your Google Client Secret");
+        }
+    }
+    if cfg.has_redacted_secrets() && cfg.google_client.is_some() {
+        authorize_calendar(cfg);
+    }
+}
+
+fn register_secrets(cfg: &Config) {
+    redact::register(&cfg.github.token);
+    cfg.github
+        .extra_tokens
+        .iter()
+        .for_each(|t| redact::register(t));
+    if let Some(client) = &cfg.google_client {
+        redact::register(&client.client_secret);
+    }
+    if let Some(token) = &cfg.google_token {
+        redact::register(&token.access_token);
+        redact::register(&token.refresh_token);
+    }
+}
+
+// precedence: --github-token > STANDUP_GITHUB_TOKEN > --github-token-file > config
+fn resolve_github_token(opt: &Opt, cfg: &Config) -> Result<String, String> {
+    if let Some(token) = &opt.github_token {
+        return Ok(token.clone());
+    }
+
+    if let Some(path) = &opt.github_token_file {
+        let token = fs::read_to_string(path)
+            .map_err(|e| format!("can not read token file {}: {}", path.display(), e))?;
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(format!("token file {} is empty", path.display()));
+        }
+        return Ok(token.to_owned());
+    }
+
+    Ok(cfg.github.token.clone())
+}
+
+fn save_report(
+    dir: &Path,
+    date: Date<Local>,
+    format: &Format,
+    force: bool,
+    output: &str,
+) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("can not create directory {}: {}", dir.display(), e))?;
+
+    let path = dir.join(format!(
+        "standup-{}.{}",
+        date.format("%Y-%m-%d"),
+        format.extension()
+    ));
+    if path.exists() && !force {
+        return Err(format!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        ));
+    }
+
+    fs::write(&path, output).map_err(|e| format!("can not write {}: {}", path.display(), e))
+}
+
+fn post_to_teams(url: &str, report: &str) -> Result<(), String> {
+    debug!("POST report to Teams webhook");
+    http::client()
+        .map_err(|e| e.to_string())?
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(String::from(report))
+        .send()
+        .map_err(|e| format!("Request to Teams webhook failed: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Incorrect response status: {}", e))?;
+    Ok(())
+}
+
+fn pipe_to(command: &str, report: &str) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("can not spawn `{}`: {}", command, e))?;
+
+    // stdin must be dropped before waiting, otherwise the command never sees EOF
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(report.as_bytes())
+            .map_err(|e| format!("can not write report to `{}`: {}", command, e))?;
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("can not wait for `{}`: {}", command, e))?;
+    if !status.success() {
+        return Err(format!("`{}` exited with {}", command, status));
     }
 
     Ok(())
 }
 
 fn main() {
+    // warnings are shown by default, RUST_LOG=debug for troubleshooting
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{} {}] {}",
+                record.level(),
+                record.target(),
+                redact::redact(&record.args().to_string())
+            )
+        })
+        .init();
+
     match run() {
         Ok(_) => (),
         Err(e) => {
-            writeln!(&mut stderr(), "{}", e).ok();
+            writeln!(&mut stderr(), "{}", redact::redact(&e.to_string())).ok();
             process::exit(1);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    // Wednesday
+    fn clock() -> FixedClock {
+        on(2019, 8, 7)
+    }
+
+    fn on(year: i32, month: u32, day: u32) -> FixedClock {
+        FixedClock(Local.ymd(year, month, day).and_hms(10, 30, 0))
+    }
+
+    fn midnight(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Local
+            .ymd(year, month, day)
+            .and_hms(0, 0, 0)
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn since_keywords() {
+        let since = |v| parse_since(v, None, &clock());
+        assert_eq!(since("yesterday"), Ok(midnight(2019, 8, 6)));
+        assert_eq!(since("friday"), Ok(midnight(2019, 8, 2)));
+        assert_eq!(since("today"), Ok(midnight(2019, 8, 7)));
+        assert_eq!(since("start-of-month"), Ok(midnight(2019, 8, 1)));
+        assert_eq!(since("end-of-month"), Ok(midnight(2019, 9, 1)));
+        assert_eq!(since("start-of-year"), Ok(midnight(2019, 1, 1)));
+        assert_eq!(since("monday"), Ok(midnight(2019, 8, 5)));
+        assert_eq!(since("wednesday"), Ok(midnight(2019, 8, 7)));
+        assert_eq!(since("thursday"), Ok(midnight(2019, 8, 1)));
+        assert_eq!(since("last-week"), Ok(midnight(2019, 7, 29)));
+        assert_eq!(since("3d"), Ok(midnight(2019, 8, 4)));
+        assert_eq!(since("2w"), Ok(midnight(2019, 7, 24)));
+        assert!(since("-1d").is_err());
+        assert!(since("d").is_err());
+        assert_eq!(since("99999999d"), Err("unsupported value"));
+    }
+
+    #[test]
+    fn since_friday_on_friday_is_today() {
+        assert_eq!(
+            parse_since("friday", None, &on(2019, 8, 9)),
+            Ok(midnight(2019, 8, 9))
+        );
+    }
+
+    #[test]
+    fn since_sprint() {
+        let sprint = config::Sprint {
+            start: NaiveDate::from_ymd(2019, 7, 1),
+            length_days: 14,
+        };
+        assert_eq!(
+            parse_since("sprint", Some(&sprint), &clock()),
+            Ok(midnight(2019, 7, 29))
+        );
+        assert_eq!(
+            parse_since("sprint", Some(&sprint), &on(2019, 7, 29)),
+            Ok(midnight(2019, 7, 29))
+        );
+        assert!(parse_since("sprint", None, &clock()).is_err());
+    }
+
+    #[test]
+    fn since_dates_and_timestamps() {
+        let since = |v| parse_since(v, None, &clock());
+        assert_eq!(since("2019-07-30"), Ok(midnight(2019, 7, 30)));
+        assert_eq!(
+            since("@1565049600"),
+            Ok(Utc.ymd(2019, 8, 6).and_hms(0, 0, 0))
+        );
+        assert!(since("2019-02-30").is_err());
+        assert!(since("last week").is_err());
+        assert!(since("@soon").is_err());
+    }
+
+    #[test]
+    fn until_values() {
+        let until = |v| parse_until_at(v, &clock());
+        assert_eq!(until("today"), Ok(midnight(2019, 8, 7)));
+        assert_eq!(until("end-of-month"), Ok(midnight(2019, 9, 1)));
+        assert_eq!(until("2019-08-05"), Ok(midnight(2019, 8, 5)));
+        assert!(until("yesterday").is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        let range = |v| parse_range(v, None, &clock());
+        assert_eq!(
+            range("2019-08-01..2019-08-05"),
+            Ok((midnight(2019, 8, 1), Some(midnight(2019, 8, 5))))
+        );
+        assert_eq!(range("2019-08-05.."), Ok((midnight(2019, 8, 5), None)));
+        assert!(range("2019-08-05").is_err());
+        assert!(range("2019-08-05..2019-08-01").is_err());
+        assert!(range("99999999d..").is_err());
+    }
+
+    #[test]
+    fn overlapping_windows_are_joined() {
+        let windows = join_windows(vec![
+            (midnight(2019, 8, 5), None),
+            (midnight(2019, 7, 1), Some(midnight(2019, 7, 10))),
+            (midnight(2019, 7, 8), Some(midnight(2019, 7, 12))),
+            (midnight(2019, 8, 1), Some(midnight(2019, 8, 6))),
+        ]);
+        assert_eq!(
+            windows,
+            vec![
+                (midnight(2019, 7, 1), Some(midnight(2019, 7, 12))),
+                (midnight(2019, 8, 1), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn december_end_of_month_is_next_year() {
+        assert_eq!(
+            period_boundary("end-of-month", Local.ymd(2019, 12, 15)),
+            Some(Local.ymd(2020, 1, 1))
+        );
+    }
+
+    #[test]
+    fn dates_are_local_midnight_in_utc() {
+        let d = parse_date("2019-08-06").unwrap();
+        assert_eq!(
+            DateTime::<Utc>::from(d.and_hms(0, 0, 0)),
+            Utc.ymd(2019, 8, 6).and_hms(0, 0, 0)
+                - Duration::seconds(i64::from(Local.ymd(2019, 8, 6).offset().local_minus_utc()))
+        );
+    }
+}
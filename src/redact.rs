@@ -0,0 +1,26 @@
+// Masks known secrets in strings that are about to be printed or logged.
+// Secrets are registered as soon as they are read from the config or cli.
+
+use std::sync::Mutex;
+
+const MASK: &str = "[REDACTED]";
+
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn register(secret: &str) {
+    // empty strings would match everywhere
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.lock().unwrap();
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(String::from(secret));
+    }
+}
+
+pub fn redact(v: &str) -> String {
+    let secrets = SECRETS.lock().unwrap();
+    secrets
+        .iter()
+        .fold(String::from(v), |acc, secret| acc.replace(secret, MASK))
+}
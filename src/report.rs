@@ -1,11 +1,64 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone)]
+use chrono::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Clone, Default, Serialize)]
 pub struct Entry {
     pub r#type: String,
+    pub number: Option<u64>,
     pub title: String,
     pub url: Option<String>,
     pub actions: Vec<String>,
+    pub labels: Vec<String>,
+    // excerpt of the description
+    pub body: Option<String>,
+    // added by the user, see --interactive
+    pub note: Option<String>,
+    // login of the PR or issue author
+    pub author: Option<String>,
+    // issues of the same repository closed by a PR, see --with-closed-issues
+    pub closes: Vec<u64>,
+    // short merge commit sha of a merged PR, see --with-sha
+    pub sha: Option<String>,
+    // conversation and review comments, see --with-comment-counts
+    pub comments: Option<u64>,
+    // reviews of own PRs, see --with-approvals
+    pub reviews: Option<ReviewCounts>,
+    // target branch of a PR
+    pub base: Option<String>,
+    // time of the latest activity
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct ReviewCounts {
+    pub approvals: u32,
+    pub changes_requested: u32,
+}
+
+impl fmt::Display for ReviewCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = if self.approvals == 1 { "" } else { "s" };
+        write!(f, "{} approval{}", self.approvals, plural)?;
+        if self.changes_requested > 0 {
+            write!(f, ", {} changes requested", self.changes_requested)?;
+        }
+        Ok(())
+    }
+}
+
+impl Entry {
+    // identifies the entry between runs, meetings don't have urls
+    pub fn key(&self) -> String {
+        match &self.url {
+            Some(url) => url.clone(),
+            None => format!("{}: {}", self.r#type, self.title),
+        }
+    }
 }
 
 impl fmt::Display for Entry {
@@ -16,6 +69,1663 @@ impl fmt::Display for Entry {
         if !self.actions.is_empty() {
             write!(f, "({}) ", self.actions.join(", "))?;
         }
-        write!(f, "{} {}", self.title, url)
+        write!(f, "{} {}", self.title, url)?;
+        if let Some(sha) = &self.sha {
+            write!(f, " @{}", sha)?;
+        }
+        if let Some(n) = self.comments {
+            write!(f, " ({} comment{})", n, if n == 1 { "" } else { "s" })?;
+        }
+        if let Some(reviews) = &self.reviews {
+            write!(f, " ({})", reviews)?;
+        }
+        if let Some(note) = &self.note {
+            write!(f, " (note: {})", note)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Report {
+    pub since: DateTime<Utc>,
+    pub until: Option<DateTime<Utc>>,
+    pub meetings: Vec<Entry>,
+    pub repos: HashMap<String, Vec<Entry>>,
+    // open items that still need action, see --todo
+    pub todo: Vec<Entry>,
+    pub sort_repos: SortRepos,
+}
+
+// order of repositories in the report, see --sort-repos
+#[derive(Clone, Copy, Default)]
+pub enum SortRepos {
+    #[default]
+    Alpha,
+    // the most entries first
+    ByCount,
+}
+
+impl SortRepos {
+    pub const VARIANTS: &'static [&'static str] = &["alpha", "by-count"];
+}
+
+impl FromStr for SortRepos {
+    type Err = String;
+
+    fn from_str(v: &str) -> Result<SortRepos, String> {
+        match v {
+            "alpha" => Ok(SortRepos::Alpha),
+            "by-count" => Ok(SortRepos::ByCount),
+            _ => Err(format!("unsupported order: {}", v)),
+        }
+    }
+}
+
+impl Report {
+    // the day the report is made for, standup usually covers the previous days
+    pub fn date(&self) -> Date<Local> {
+        self.until
+            .map(|d| d.with_timezone(&Local).date())
+            .unwrap_or_else(Local::today)
+    }
+
+    // meetings, repositories and to do items as headings with entries,
+    // empty meetings and to do sections are omitted
+    pub fn sections(&self) -> Vec<(&str, &[Entry])> {
+        let mut sections: Vec<(&str, &[Entry])> = Vec::new();
+        if !self.meetings.is_empty() {
+            sections.push(("Meetings", &self.meetings));
+        }
+        for (repo, entries) in self.sorted_repos() {
+            sections.push((repo, entries));
+        }
+        if !self.todo.is_empty() {
+            sections.push(("To do", &self.todo));
+        }
+        sections
+    }
+
+    // names are compared last to keep the order stable between runs
+    pub fn sorted_repos(&self) -> Vec<(&String, &Vec<Entry>)> {
+        let mut repos: Vec<(&String, &Vec<Entry>)> = self.repos.iter().collect();
+        match self.sort_repos {
+            SortRepos::Alpha => repos.sort_by(|a, b| a.0.cmp(b.0)),
+            SortRepos::ByCount => {
+                repos.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)))
+            }
+        }
+        repos
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.meetings
+            .iter()
+            .chain(self.repos.values().flatten())
+            .map(Entry::key)
+            .collect()
+    }
+
+    // drops entries reported before, repositories left without entries are dropped too
+    pub fn retain_new(&mut self, seen: &HashSet<String>) {
+        self.meetings.retain(|e| !seen.contains(&e.key()));
+        for entries in self.repos.values_mut() {
+            entries.retain(|e| !seen.contains(&e.key()));
+        }
+        self.repos.retain(|_, entries| !entries.is_empty());
+    }
+
+    // reports of single days in order, entries without a time, meetings
+    // and to do items go into the last report without a date
+    pub fn split_by_day(self) -> Vec<(Option<Date<Local>>, Report)> {
+        let (since, until, sort_repos) = (self.since, self.until, self.sort_repos);
+        let part = || Report {
+            since,
+            until,
+            meetings: Vec::new(),
+            repos: HashMap::new(),
+            todo: Vec::new(),
+            sort_repos,
+        };
+        let mut days: BTreeMap<Option<Date<Local>>, Report> = BTreeMap::new();
+        for (repo, entries) in self.repos {
+            for e in entries {
+                let day = e.created_at.map(|d| d.with_timezone(&Local).date());
+                days.entry(day)
+                    .or_insert_with(part)
+                    .repos
+                    .entry(repo.clone())
+                    .or_default()
+                    .push(e);
+            }
+        }
+        if !self.meetings.is_empty() || !self.todo.is_empty() {
+            let other = days.entry(None).or_insert_with(part);
+            other.meetings = self.meetings;
+            other.todo = self.todo;
+        }
+
+        // None sorts first in the map
+        let mut res: Vec<(Option<Date<Local>>, Report)> = days.into_iter().collect();
+        if res.first().is_some_and(|(day, _)| day.is_none()) {
+            let other = res.remove(0);
+            res.push(other);
+        }
+        res
+    }
+
+    // drops entries that happened outside of the hours in local time,
+    // entries without a time are kept
+    pub fn retain_within_hours(&mut self, start: NaiveTime, end: NaiveTime) {
+        let within = |e: &Entry| {
+            let t = match e.created_at {
+                Some(d) => d.with_timezone(&Local).time(),
+                None => return true,
+            };
+            if start <= end {
+                t >= start && t < end
+            } else {
+                t >= start || t < end
+            }
+        };
+        for entries in self.repos.values_mut() {
+            entries.retain(within);
+        }
+        self.repos.retain(|_, entries| !entries.is_empty());
+    }
+
+    // shows repositories under their display names,
+    // entries of repositories sharing a name are put together
+    pub fn alias_repos(&mut self, aliases: &HashMap<String, String>) {
+        if aliases.is_empty() {
+            return;
+        }
+        let mut repos: HashMap<String, Vec<Entry>> = HashMap::new();
+        for (name, entries) in self.repos.drain() {
+            let name = aliases.get(&name).cloned().unwrap_or(name);
+            repos.entry(name).or_default().extend(entries);
+        }
+        self.repos = repos;
+    }
+
+    // replaces repository names, titles and urls with generic placeholders,
+    // numbering is stable within a single run
+    pub fn anonymize(&mut self) {
+        let mut counters: HashMap<String, usize> = HashMap::new();
+        let mut anonymize_entry = |e: &mut Entry| {
+            let n = counters.entry(e.r#type.clone()).or_insert(0);
+            *n += 1;
+            e.title = format!("{} {}", e.r#type, n);
+            e.number = None;
+            e.url = None;
+            e.labels.clear();
+            e.body = None;
+            e.note = None;
+            e.author = None;
+            e.sha = None;
+            e.base = None;
+            e.closes.clear();
+        };
+
+        for e in self.meetings.iter_mut().chain(self.todo.iter_mut()) {
+            anonymize_entry(e);
+        }
+
+        let mut names: Vec<String> = self.repos.keys().cloned().collect();
+        names.sort();
+        let aliases: HashMap<String, String> = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (name, format!("repo-{}", i + 1)))
+            .collect();
+
+        let mut repos = HashMap::new();
+        for (repo, mut entries) in self.repos.drain() {
+            for e in entries.iter_mut() {
+                anonymize_entry(e);
+            }
+            repos.insert(aliases[&repo].clone(), entries);
+        }
+        self.repos = repos;
+    }
+}
+
+// adds entries of another fetch, an entry present in both gets actions
+// of both and the later time
+pub fn merge_repos(into: &mut HashMap<String, Vec<Entry>>, from: HashMap<String, Vec<Entry>>) {
+    for (repo, entries) in from {
+        let existing = into.entry(repo).or_default();
+        for e in entries {
+            let key = e.key();
+            match existing.iter_mut().find(|x| x.key() == key) {
+                Some(x) => {
+                    for action in e.actions {
+                        if !x.actions.contains(&action) {
+                            x.actions.push(action);
+                        }
+                    }
+                    x.created_at = x.created_at.max(e.created_at);
+                }
+                None => existing.push(e),
+            }
+        }
+    }
+}
+
+// entries with an action listed earlier in priority go first,
+// the rest keeps the order of numbers to stay deterministic between runs
+pub fn sort_entries(entries: &mut [Entry], priority: &[String]) {
+    let rank = |e: &Entry| {
+        e.actions
+            .iter()
+            .filter_map(|a| priority.iter().position(|p| p.eq_ignore_ascii_case(a)))
+            .min()
+            .unwrap_or(priority.len())
+    };
+    entries.sort_by(|a, b| {
+        rank(a)
+            .cmp(&rank(b))
+            .then(a.number.cmp(&b.number))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+}
+
+// Output formats
+
+pub enum Format {
+    Text,
+    SlackBlocks,
+    Prometheus,
+    Table,
+    Email,
+    Repos,
+    Ndjson,
+    Alfred,
+    Confluence,
+    Atom,
+    GithubComment,
+    Teams,
+    Minimal,
+    HtmlStandalone,
+    SlackMrkdwn,
+    Json,
+    Markdown,
+    #[cfg(feature = "toml")]
+    Toml,
+}
+
+impl Format {
+    pub const VARIANTS: &'static [&'static str] = &[
+        "text",
+        "slack-blocks",
+        "prometheus",
+        "table",
+        "email",
+        "repos",
+        "ndjson",
+        "alfred",
+        "confluence",
+        "atom",
+        "github-comment",
+        "teams",
+        "minimal",
+        "html-standalone",
+        "toml",
+        "slack-mrkdwn",
+        "json",
+        "markdown",
+    ];
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Text => "md",
+            Format::SlackBlocks => "json",
+            Format::Prometheus => "prom",
+            Format::Table => "txt",
+            Format::Email => "eml",
+            Format::Repos => "txt",
+            Format::Ndjson => "ndjson",
+            Format::Alfred => "json",
+            Format::Confluence => "txt",
+            Format::Atom => "xml",
+            Format::GithubComment => "md",
+            Format::Teams => "json",
+            Format::Minimal => "txt",
+            Format::HtmlStandalone => "html",
+            Format::SlackMrkdwn => "txt",
+            Format::Json => "json",
+            Format::Markdown => "md",
+            #[cfg(feature = "toml")]
+            Format::Toml => "toml",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(v: &str) -> Result<Format, String> {
+        match v {
+            "text" => Ok(Format::Text),
+            "slack-blocks" => Ok(Format::SlackBlocks),
+            "prometheus" => Ok(Format::Prometheus),
+            "table" => Ok(Format::Table),
+            "email" => Ok(Format::Email),
+            "repos" => Ok(Format::Repos),
+            "ndjson" => Ok(Format::Ndjson),
+            "alfred" => Ok(Format::Alfred),
+            "confluence" => Ok(Format::Confluence),
+            "atom" => Ok(Format::Atom),
+            "github-comment" => Ok(Format::GithubComment),
+            "teams" => Ok(Format::Teams),
+            "minimal" => Ok(Format::Minimal),
+            "html-standalone" => Ok(Format::HtmlStandalone),
+            "slack-mrkdwn" => Ok(Format::SlackMrkdwn),
+            "json" => Ok(Format::Json),
+            "markdown" => Ok(Format::Markdown),
+            #[cfg(feature = "toml")]
+            "toml" => Ok(Format::Toml),
+            #[cfg(not(feature = "toml"))]
+            "toml" => Err(String::from(
+                "toml format requires building with --features toml",
+            )),
+            _ => Err(format!("unsupported format: {}", v)),
+        }
+    }
+}
+
+// decorations of the text report
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub name: &'static str,
+    // before repository names and meetings
+    bullet: &'static str,
+    // before entries, after the indent
+    item: &'static str,
+    indent: &'static str,
+    // before bodies of entries
+    quote: &'static str,
+    // line between sections
+    separator: Option<&'static str>,
+}
+
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        name: "plain",
+        bullet: "* ",
+        item: "- ",
+        indent: "  ",
+        quote: "> ",
+        separator: None,
+    },
+    Theme {
+        name: "boxed",
+        bullet: "■ ",
+        item: "• ",
+        indent: "│ ",
+        quote: "> ",
+        separator: Some("────────────────────────────────"),
+    },
+    Theme {
+        name: "minimal",
+        bullet: "",
+        item: "",
+        indent: "  ",
+        quote: "",
+        separator: None,
+    },
+];
+
+impl Theme {
+    pub const VARIANTS: &'static [&'static str] = &["plain", "boxed", "minimal"];
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        THEMES[0]
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(v: &str) -> Result<Theme, String> {
+        THEMES
+            .iter()
+            .find(|t| t.name == v)
+            .copied()
+            .ok_or_else(|| format!("unsupported theme: {}", v))
+    }
+}
+
+// tweaks that aren't specific to a single format
+#[derive(Default)]
+pub struct RenderOptions {
+    // put repositories with a single entry on one line
+    pub flat_single: bool,
+    // append how long ago each entry happened, e.g. (18 hours ago)
+    pub relative_time: bool,
+    // base url of Jira to link issue keys in titles to, e.g. https://example.atlassian.net
+    pub jira_url: Option<String>,
+    // decorations of the text report, see --theme
+    pub theme: Theme,
+    // add `by @author` to entries authored by someone else than me
+    pub show_authors: bool,
+    // logins of the reported user
+    pub me: Vec<String>,
+    // the calendar was fetched and had no events, said explicitly to tell it from a failure
+    pub empty_calendar: bool,
+}
+
+// Jira issue keys like PROJ-123 in a title, a key is uppercase letters
+// and digits starting with a letter, a dash and a number
+fn jira_keys(title: &str) -> Vec<String> {
+    let chars: Vec<char> = title.chars().collect();
+    let mut keys: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let boundary = i == 0 || !chars[i - 1].is_alphanumeric();
+        if boundary && chars[i].is_ascii_uppercase() {
+            let mut j = i;
+            while j < chars.len() && (chars[j].is_ascii_uppercase() || chars[j].is_ascii_digit()) {
+                j += 1;
+            }
+            let mut k = j + 1;
+            while k < chars.len() && chars[k].is_ascii_digit() {
+                k += 1;
+            }
+            let ends = k == chars.len() || !chars[k].is_alphanumeric();
+            if j < chars.len() && chars[j] == '-' && k > j + 1 && ends {
+                let key: String = chars[i..k].iter().collect();
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+                i = k;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    keys
+}
+
+// keys with their urls, titles are already links to GitHub,
+// so formats add the keys after the title, see --link-jira
+fn jira_links(title: &str, base_url: Option<&str>) -> Vec<(String, String)> {
+    let base_url = match base_url {
+        Some(url) => url.trim_end_matches('/'),
+        None => return Vec::new(),
+    };
+    jira_keys(title)
+        .into_iter()
+        .map(|key| {
+            let url = format!("{}/browse/{}", base_url, key);
+            (key, url)
+        })
+        .collect()
+}
+
+fn time_ago(d: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now.signed_duration_since(d);
+    let (n, unit) = if elapsed.num_days() > 0 {
+        (elapsed.num_days(), "day")
+    } else if elapsed.num_hours() > 0 {
+        (elapsed.num_hours(), "hour")
+    } else if elapsed.num_minutes() > 0 {
+        (elapsed.num_minutes(), "minute")
+    } else {
+        return String::from("just now");
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+pub fn render(format: &Format, report: &Report, opts: &RenderOptions) -> String {
+    match format {
+        Format::Text => render_text(report, opts),
+        Format::SlackBlocks => render_slack_blocks(report, opts.jira_url.as_deref()),
+        Format::Prometheus => render_prometheus(report),
+        Format::Table => render_table(report),
+        Format::Email => render_email(report, opts),
+        Format::Repos => render_repos(report),
+        Format::Ndjson => render_ndjson(report),
+        Format::Alfred => render_alfred(report),
+        Format::Confluence => render_confluence(report, opts.jira_url.as_deref()),
+        Format::Atom => render_atom(report),
+        Format::GithubComment => render_github_comment(report, opts.jira_url.as_deref()),
+        Format::Teams => render_teams(report),
+        Format::Minimal => render_minimal(report),
+        Format::HtmlStandalone => render_html_standalone(report, opts.jira_url.as_deref()),
+        Format::SlackMrkdwn => render_slack_mrkdwn(report, opts.jira_url.as_deref()),
+        Format::Json => render_json(report),
+        Format::Markdown => render_markdown(report, opts.jira_url.as_deref()),
+        #[cfg(feature = "toml")]
+        Format::Toml => render_toml(report),
+    }
+}
+
+fn render_text(report: &Report, opts: &RenderOptions) -> String {
+    let now = Utc::now();
+    let line = |e: &Entry| {
+        let mut line = e.to_string();
+        for (_, url) in jira_links(&e.title, opts.jira_url.as_deref()) {
+            line.push_str(&format!(" {}", url));
+        }
+        if let (true, Some(author)) = (opts.show_authors, &e.author) {
+            if !opts.me.iter().any(|l| l.eq_ignore_ascii_case(author)) {
+                line.push_str(&format!(" by @{}", author));
+            }
+        }
+        match e.created_at {
+            Some(d) if opts.relative_time => format!("{} ({})", line, time_ago(d, now)),
+            _ => line,
+        }
+    };
+
+    let t = &opts.theme;
+    let mut out = String::new();
+    let separate = |out: &mut String| {
+        if let (Some(sep), false) = (t.separator, out.is_empty()) {
+            out.push_str(&format!("{}\n", sep));
+        }
+    };
+    if !report.meetings.is_empty() || opts.empty_calendar {
+        out.push_str(&format!("{}Meetings:\n", t.bullet));
+        for e in &report.meetings {
+            out.push_str(&format!("{}{}{}\n", t.indent, t.item, line(e)));
+        }
+        if report.meetings.is_empty() {
+            out.push_str(&format!("{}{}(no meetings)\n", t.indent, t.item));
+        }
+    }
+    for (repo, entries) in report.sorted_repos() {
+        separate(&mut out);
+        if opts.flat_single && entries.len() == 1 {
+            let e = &entries[0];
+            out.push_str(&format!("{}{}: {}\n", t.bullet, repo, line(e)));
+            if let Some(body) = &e.body {
+                out.push_str(&format!("{}{}{}\n", t.indent, t.quote, body));
+            }
+            for n in &e.closes {
+                out.push_str(&format!("{}{}closes #{}\n", t.indent, t.item, n));
+            }
+            continue;
+        }
+
+        out.push_str(&format!("{}{}:\n", t.bullet, repo));
+        for e in entries {
+            out.push_str(&format!("{}{}{}\n", t.indent, t.item, line(e)));
+            if let Some(body) = &e.body {
+                out.push_str(&format!("{0}{0}{1}{2}\n", t.indent, t.quote, body));
+            }
+            for n in &e.closes {
+                out.push_str(&format!("{0}{0}{1}closes #{2}\n", t.indent, t.item, n));
+            }
+        }
+    }
+    if !report.todo.is_empty() {
+        separate(&mut out);
+        out.push_str(&format!("{}To do:\n", t.bullet));
+        for e in &report.todo {
+            out.push_str(&format!("{}{}{}\n", t.indent, t.item, line(e)));
+        }
+    }
+    out
+}
+
+// to be read aloud: titles and what was done, no types, urls or repositories
+fn render_minimal(report: &Report) -> String {
+    let mut out = String::new();
+    for (_, entries) in report.sections() {
+        for e in entries {
+            if e.actions.is_empty() {
+                out.push_str(&format!("{}\n", e.title));
+            } else {
+                out.push_str(&format!("{} — {}\n", e.title, e.actions.join(", ")));
+            }
+        }
+    }
+    out
+}
+
+fn render_repos(report: &Report) -> String {
+    report
+        .sorted_repos()
+        .into_iter()
+        .map(|(name, _)| format!("{}\n", name))
+        .collect()
+}
+
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    repo: Option<&'a str>,
+    #[serde(flatten)]
+    entry: &'a Entry,
+}
+
+// http://ndjson.org/
+fn render_ndjson(report: &Report) -> String {
+    let meetings = report.meetings.iter().map(|e| (None, e));
+    let entries = report
+        .sorted_repos()
+        .into_iter()
+        .flat_map(|(repo, entries)| entries.iter().map(move |e| (Some(repo.as_str()), e)));
+
+    let mut out = String::new();
+    for (repo, entry) in meetings.chain(entries) {
+        let line = serde_json::to_string(&NdjsonLine { repo, entry })
+            .expect("entries are always serializable");
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+// https://confluence.atlassian.com/doc/confluence-wiki-markup-251003035.html
+fn confluence_escape(v: &str) -> String {
+    let mut out = String::with_capacity(v.len());
+    for c in v.chars() {
+        if "[]|*_{}".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn confluence_line(e: &Entry, jira: Option<&str>) -> String {
+    let mut line = format!("* {} ", e.r#type);
+    if !e.actions.is_empty() {
+        line.push_str(&format!("*{}* ", e.actions.join(", ")));
+    }
+    match &e.url {
+        Some(url) => line.push_str(&format!("[{}|{}]", confluence_escape(&e.title), url)),
+        None => line.push_str(&confluence_escape(&e.title)),
+    }
+    for (key, url) in jira_links(&e.title, jira) {
+        line.push_str(&format!(" [{}|{}]", key, url));
+    }
+    if let Some(note) = &e.note {
+        line.push_str(&format!(" _{}_", confluence_escape(note)));
+    }
+    line.push('\n');
+    line
+}
+
+fn render_confluence(report: &Report, jira: Option<&str>) -> String {
+    let mut out = String::new();
+    for (heading, entries) in report.sections() {
+        out.push_str(&format!("h3. {}\n", heading));
+        entries
+            .iter()
+            .for_each(|e| out.push_str(&confluence_line(e, jira)));
+    }
+    out
+}
+
+// html is allowed in github comments, a title like Vec<T> would be taken for a tag
+fn html_text_escape(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// collapsible section per repository with a task list,
+// markdown inside <details> is rendered only after an empty line
+fn render_github_comment(report: &Report, jira: Option<&str>) -> String {
+    let section = |heading: &str, entries: &[Entry]| {
+        let mut out = format!(
+            "<details><summary>{}</summary>\n\n",
+            html_text_escape(heading)
+        );
+        for e in entries {
+            out.push_str(&format!("- [ ] **{}** ", e.r#type));
+            if !e.actions.is_empty() {
+                out.push_str(&format!("({}) ", e.actions.join(", ")));
+            }
+            let title = markdown_escape(&html_text_escape(&e.title));
+            match &e.url {
+                Some(url) => out.push_str(&format!("[{}]({})", title, url)),
+                None => out.push_str(&title),
+            }
+            for (key, url) in jira_links(&e.title, jira) {
+                out.push_str(&format!(" [{}]({})", key, url));
+            }
+            if let Some(note) = &e.note {
+                out.push_str(&format!(" _{}_", note));
+            }
+            out.push('\n');
+        }
+        out.push_str("\n</details>\n");
+        out
+    };
+
+    report
+        .sections()
+        .into_iter()
+        .map(|(heading, entries)| section(heading, entries))
+        .collect()
+}
+
+// brackets would end the link text early
+fn markdown_escape(v: &str) -> String {
+    v.replace('[', "\\[").replace(']', "\\]")
+}
+
+fn markdown_line(e: &Entry, jira: Option<&str>) -> String {
+    let mut line = format!("- **{}** ", e.r#type);
+    if !e.actions.is_empty() {
+        line.push_str(&format!("({}) ", e.actions.join(", ")));
+    }
+    match &e.url {
+        Some(url) => line.push_str(&format!("[{}]({})", markdown_escape(&e.title), url)),
+        None => line.push_str(&e.title),
+    }
+    for (key, url) in jira_links(&e.title, jira) {
+        line.push_str(&format!(" [{}]({})", key, url));
+    }
+    if let Some(note) = &e.note {
+        line.push_str(&format!(" _{}_", note));
+    }
+    line
+}
+
+// sections as ### headings to paste into Notion or a Markdown doc
+fn render_markdown(report: &Report, jira: Option<&str>) -> String {
+    report
+        .sections()
+        .into_iter()
+        .map(|(heading, entries)| {
+            let lines: Vec<String> = entries.iter().map(|e| markdown_line(e, jira)).collect();
+            format!("### {}\n\n{}\n", heading, lines.join("\n"))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn xml_escape(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// sections as headings with lists of entries, the escaping is the same as for xml
+fn render_html(report: &Report, jira: Option<&str>) -> String {
+    let mut out = String::new();
+    for (title, entries) in report.sections() {
+        out.push_str(&format!("<h2>{}</h2>\n<ul>\n", xml_escape(title)));
+        for e in entries {
+            out.push_str(&format!(
+                "  <li><span class=\"type\">{}</span> ",
+                xml_escape(&e.r#type)
+            ));
+            if !e.actions.is_empty() {
+                out.push_str(&format!(
+                    "<span class=\"actions\">{}</span> ",
+                    xml_escape(&e.actions.join(", "))
+                ));
+            }
+            match &e.url {
+                Some(url) => out.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    xml_escape(url),
+                    xml_escape(&e.title)
+                )),
+                None => out.push_str(&xml_escape(&e.title)),
+            }
+            for (key, url) in jira_links(&e.title, jira) {
+                out.push_str(&format!(
+                    " <a class=\"jira\" href=\"{}\">{}</a>",
+                    xml_escape(&url),
+                    xml_escape(&key)
+                ));
+            }
+            if let Some(note) = &e.note {
+                out.push_str(&format!(" <em>{}</em>", xml_escape(note)));
+            }
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n");
+    }
+    out
+}
+
+const HTML_STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; color: #24292e; line-height: 1.5; }
+h1 { font-size: 1.6em; border-bottom: 1px solid #e1e4e8; padding-bottom: .3em; }
+h2 { font-size: 1.2em; margin-top: 1.5em; }
+ul { padding-left: 1.2em; }
+li { margin: .2em 0; }
+a { color: #0366d6; text-decoration: none; }
+.type { font-size: .8em; color: #586069; text-transform: uppercase; }
+.actions { color: #22863a; }
+@media print { body { margin: 0; max-width: none; } a { color: inherit; } }
+";
+
+// a complete page to save or print, titled with the period of the report
+fn render_html_standalone(report: &Report, jira: Option<&str>) -> String {
+    let since = report.since.with_timezone(&Local).format("%Y-%m-%d");
+    let title = match report.until {
+        Some(until) => format!(
+            "Standup {} – {}",
+            since,
+            until.with_timezone(&Local).format("%Y-%m-%d")
+        ),
+        None => format!("Standup since {}", since),
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n{2}</body>\n</html>\n",
+        xml_escape(&title),
+        HTML_STYLE,
+        render_html(report, jira)
+    )
+}
+
+// the whole report as one document, shared by json and toml
+#[derive(Serialize)]
+struct Document<'a> {
+    since: DateTime<Utc>,
+    until: Option<DateTime<Utc>>,
+    meetings: &'a [Entry],
+    repos: BTreeMap<&'a str, &'a [Entry]>,
+    todo: &'a [Entry],
+}
+
+impl<'a> Document<'a> {
+    fn new(report: &'a Report) -> Document<'a> {
+        Document {
+            since: report.since,
+            until: report.until,
+            meetings: &report.meetings,
+            repos: report
+                .repos
+                .iter()
+                .map(|(repo, entries)| (repo.as_str(), entries.as_slice()))
+                .collect(),
+            todo: &report.todo,
+        }
+    }
+}
+
+fn render_json(report: &Report) -> String {
+    let mut out = serde_json::to_string_pretty(&Document::new(report))
+        .expect("entries are always serializable");
+    out.push('\n');
+    out
+}
+
+// repositories are tables with entries as arrays of tables, e.g. [[repos."org/repo"]]
+#[cfg(feature = "toml")]
+fn render_toml(report: &Report) -> String {
+    // going through Value puts plain values before tables as toml requires
+    toml::Value::try_from(Document::new(report))
+        .and_then(|v| toml::to_string(&v))
+        .expect("entries are always serializable")
+}
+
+// https://tools.ietf.org/html/rfc4287
+fn render_atom(report: &Report) -> String {
+    let date = report.date().format("%Y-%m-%d");
+    let entries: Vec<(Option<&str>, &Entry)> = report
+        .meetings
+        .iter()
+        .map(|e| (None, e))
+        .chain(
+            report
+                .sorted_repos()
+                .into_iter()
+                .flat_map(|(repo, entries)| entries.iter().map(move |e| (Some(repo.as_str()), e))),
+        )
+        .collect();
+    // meetings don't have a time, they are dated by the start of the period
+    let updated = |e: &Entry| e.created_at.unwrap_or(report.since);
+    let feed_updated = entries
+        .iter()
+        .map(|(_, e)| updated(e))
+        .max()
+        .unwrap_or(report.since);
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!("  <title>Standup {}</title>\n", date));
+    out.push_str(&format!("  <id>urn:standup-rs:{}</id>\n", date));
+    out.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        feed_updated.to_rfc3339_opts(SecondsFormat::Secs, true)
+    ));
+    for (i, (repo, e)) in entries.iter().enumerate() {
+        let mut summary = e.r#type.clone();
+        if !e.actions.is_empty() {
+            summary.push_str(&format!(" ({})", e.actions.join(", ")));
+        }
+        if let Some(repo) = repo {
+            summary.push_str(&format!(" in {}", repo));
+        }
+
+        out.push_str("  <entry>\n");
+        out.push_str(&format!("    <title>{}</title>\n", xml_escape(&e.title)));
+        match &e.url {
+            Some(url) => {
+                out.push_str(&format!("    <link href=\"{}\"/>\n", xml_escape(url)));
+                out.push_str(&format!("    <id>{}</id>\n", xml_escape(url)));
+            }
+            None => out.push_str(&format!("    <id>urn:standup-rs:{}:{}</id>\n", date, i + 1)),
+        }
+        out.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            updated(e).to_rfc3339_opts(SecondsFormat::Secs, true)
+        ));
+        out.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            xml_escape(&summary)
+        ));
+        out.push_str("  </entry>\n");
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+// Script Filter JSON format of Alfred workflows
+// https://www.alfredapp.com/help/workflows/inputs/script-filter/json/
+fn render_alfred(report: &Report) -> String {
+    let entries = report.meetings.iter().chain(
+        report
+            .sorted_repos()
+            .into_iter()
+            .flat_map(|(_, entries)| entries),
+    );
+
+    let items: Vec<serde_json::Value> = entries
+        .map(|e| {
+            let mut subtitle = e.r#type.clone();
+            if !e.actions.is_empty() {
+                subtitle.push_str(&format!(" ({})", e.actions.join(", ")));
+            }
+            let mut item = json!({
+                "title": e.title,
+                "subtitle": subtitle,
+            });
+            match &e.url {
+                Some(url) => item["arg"] = json!(url),
+                None => item["valid"] = json!(false),
+            }
+            item
+        })
+        .collect();
+
+    let mut out = serde_json::to_string_pretty(&json!({ "items": items }))
+        .expect("json values are always serializable");
+    out.push('\n');
+    out
+}
+
+// message headers are separated from the body by an empty line (RFC 5322),
+// so the output can be piped into sendmail as is
+fn render_email(report: &Report, opts: &RenderOptions) -> String {
+    format!(
+        "Subject: Standup {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+        report.date().format("%Y-%m-%d"),
+        render_text(report, opts)
+    )
+}
+
+// https://api.slack.com/reference/surfaces/formatting#escaping
+fn slack_escape(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn slack_line(e: &Entry, jira: Option<&str>) -> String {
+    let mut line = format!("• *{}* ", e.r#type);
+    if !e.actions.is_empty() {
+        line.push_str(&format!("({}) ", e.actions.join(", ")));
+    }
+    match &e.url {
+        Some(url) => line.push_str(&format!("<{}|{}>", url, slack_escape(&e.title))),
+        None => line.push_str(&slack_escape(&e.title)),
+    }
+    for (key, url) in jira_links(&e.title, jira) {
+        line.push_str(&format!(" <{}|{}>", url, key));
+    }
+    if let Some(note) = &e.note {
+        line.push_str(&format!(" _{}_", slack_escape(note)));
+    }
+    line
+}
+
+fn slack_mrkdwn(heading: &str, entries: &[Entry], jira: Option<&str>) -> String {
+    let mut text = format!("*{}*", slack_escape(heading));
+    for e in entries {
+        text.push('\n');
+        text.push_str(&slack_line(e, jira));
+    }
+    text
+}
+
+fn slack_section(heading: &str, entries: &[Entry], jira: Option<&str>) -> serde_json::Value {
+    json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": slack_mrkdwn(heading, entries, jira) },
+    })
+}
+
+// message text to paste into Slack, links are <url|title> and bold is *single*
+// https://api.slack.com/reference/surfaces/formatting
+fn render_slack_mrkdwn(report: &Report, jira: Option<&str>) -> String {
+    report
+        .sections()
+        .into_iter()
+        .map(|(heading, entries)| format!("{}\n", slack_mrkdwn(heading, entries, jira)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// https://api.slack.com/block-kit
+fn render_slack_blocks(report: &Report, jira: Option<&str>) -> String {
+    let mut blocks = Vec::new();
+    for (heading, entries) in report.sections() {
+        if !blocks.is_empty() {
+            blocks.push(json!({ "type": "divider" }));
+        }
+        blocks.push(slack_section(heading, entries, jira));
+    }
+
+    let mut out = serde_json::to_string_pretty(&json!({ "blocks": blocks }))
+        .expect("json values are always serializable");
+    out.push('\n');
+    out
+}
+
+fn teams_container(heading: &str, entries: &[Entry]) -> serde_json::Value {
+    let mut items = vec![json!({
+        "type": "TextBlock",
+        "text": heading,
+        "weight": "bolder",
+        "wrap": true,
+    })];
+    for e in entries {
+        let text = match &e.url {
+            Some(url) => format!("[{}]({})", markdown_escape(&e.title), url),
+            None => e.title.clone(),
+        };
+        items.push(json!({ "type": "TextBlock", "text": text, "wrap": true }));
+        if !e.actions.is_empty() {
+            items.push(json!({
+                "type": "FactSet",
+                "facts": [{ "title": e.r#type, "value": e.actions.join(", ") }],
+            }));
+        }
+    }
+    json!({ "type": "Container", "items": items })
+}
+
+// adaptive card wrapped into a message accepted by incoming webhooks
+// https://learn.microsoft.com/en-us/microsoftteams/platform/webhooks-and-connectors/how-to/connectors-using
+fn render_teams(report: &Report) -> String {
+    let body: Vec<serde_json::Value> = report
+        .sections()
+        .into_iter()
+        .map(|(heading, entries)| teams_container(heading, entries))
+        .collect();
+
+    let message = json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.2",
+                "body": body,
+            },
+        }],
+    });
+    let mut out =
+        serde_json::to_string_pretty(&message).expect("json values are always serializable");
+    out.push('\n');
+    out
+}
+
+fn metric_name(r#type: &str, action: &str) -> String {
+    let name = match (r#type, action) {
+        ("PR", "reviewed") => String::from("standup_reviews_total"),
+        ("PR", a) if a.contains("merged") => String::from("standup_prs_merged_total"),
+        (_, "") => format!("standup_{}s_total", r#type),
+        _ => format!("standup_{}s_{}_total", r#type, action),
+    };
+    // https://prometheus.io/docs/concepts/data_model/#metric-names-and-labels
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// https://prometheus.io/docs/instrumenting/exposition_formats/
+fn render_prometheus(report: &Report) -> String {
+    let mut counters: BTreeMap<String, usize> = BTreeMap::new();
+    for e in &report.meetings {
+        *counters.entry(metric_name(&e.r#type, "")).or_insert(0) += 1;
+    }
+    for entries in report.repos.values() {
+        for e in entries {
+            for action in &e.actions {
+                *counters.entry(metric_name(&e.r#type, action)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let date = report.since.with_timezone(&Local).format("%Y-%m-%d");
+    let mut out = String::new();
+    for (name, value) in counters {
+        out.push_str(&format!("# TYPE {} counter\n", name));
+        out.push_str(&format!("{}{{date=\"{}\"}} {}\n", name, date, value));
+    }
+    out
+}
+
+const TABLE_SEPARATOR: &str = "  ";
+const MIN_TITLE_WIDTH: usize = 20;
+
+// COLUMNS is exported by most shells, fallback is wide enough for an average title
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(120)
+}
+
+pub fn truncate(v: &str, width: usize) -> String {
+    if v.chars().count() <= width {
+        return String::from(v);
+    }
+    let mut res: String = v.chars().take(width.saturating_sub(1)).collect();
+    res.push('…');
+    res
+}
+
+fn render_table(report: &Report) -> String {
+    let row = |repo: &str, e: &Entry| {
+        [
+            e.r#type.clone(),
+            e.number.map(|n| format!("#{}", n)).unwrap_or_default(),
+            e.title.clone(),
+            e.actions.join(", "),
+            String::from(repo),
+        ]
+    };
+
+    let mut rows = vec![[
+        String::from("TYPE"),
+        String::from("NUMBER"),
+        String::from("TITLE"),
+        String::from("ACTIONS"),
+        String::from("REPO"),
+    ]];
+    rows.extend(report.meetings.iter().map(|e| row("", e)));
+    for (repo, entries) in report.sorted_repos() {
+        rows.extend(entries.iter().map(|e| row(repo, e)));
+    }
+
+    let mut widths = [0; 5];
+    for r in &rows {
+        for (i, cell) in r.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    // only the title is truncated, other columns are short
+    let others: usize = widths
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 2)
+        .map(|(_, w)| w)
+        .sum();
+    let available = terminal_width().saturating_sub(others + TABLE_SEPARATOR.len() * 4);
+    widths[2] = widths[2].min(available.max(MIN_TITLE_WIDTH));
+
+    let mut out = String::new();
+    for r in rows {
+        let cells: Vec<String> = r
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{:<width$}", truncate(cell, widths[i]), width = widths[i]))
+            .collect();
+        out.push_str(cells.join(TABLE_SEPARATOR).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(number: u64, actions: &[&str]) -> Entry {
+        Entry {
+            r#type: String::from("PR"),
+            number: Some(number),
+            title: format!("PR {}", number),
+            actions: actions.iter().map(|a| String::from(*a)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn escapes_github_comment_titles() {
+        let mut repos = HashMap::new();
+        repos.insert(
+            String::from("org/a<b>"),
+            vec![Entry {
+                title: String::from("Fix [WIP] Vec<T> & co"),
+                url: Some(String::from("https://github.com/org/a/pull/1")),
+                ..entry(1, &[])
+            }],
+        );
+        let report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: Vec::new(),
+            repos,
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+        let out = render_github_comment(&report, None);
+        assert!(out.starts_with("<details><summary>org/a&lt;b&gt;</summary>"));
+        assert!(
+            out.contains("[Fix \\[WIP\\] Vec&lt;T&gt; &amp; co](https://github.com/org/a/pull/1)")
+        );
+    }
+
+    #[test]
+    fn escapes_teams_link_titles() {
+        let container = teams_container(
+            "org/a",
+            &[Entry {
+                title: String::from("Fix [WIP] parser"),
+                url: Some(String::from("https://github.com/org/a/pull/1")),
+                ..entry(1, &[])
+            }],
+        );
+        assert_eq!(
+            container["items"][1]["text"],
+            "[Fix \\[WIP\\] parser](https://github.com/org/a/pull/1)"
+        );
+    }
+
+    #[test]
+    fn anonymizes_entries() {
+        let mut repos = HashMap::new();
+        repos.insert(
+            String::from("customer-x/backend"),
+            vec![Entry {
+                url: Some(String::from("https://github.com/customer-x/backend/pull/7")),
+                author: Some(String::from("alice")),
+                sha: Some(String::from("abc1234")),
+                base: Some(String::from("customer-x/hotfix")),
+                closes: vec![3],
+                ..entry(7, &["opened"])
+            }],
+        );
+        let mut report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: Vec::new(),
+            repos,
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+
+        report.anonymize();
+        let e = &report.repos["repo-1"][0];
+        assert_eq!(e.title, "PR 1");
+        assert_eq!(e.number, None);
+        assert_eq!(e.url, None);
+        assert_eq!(e.author, None);
+        assert_eq!(e.sha, None);
+        assert_eq!(e.base, None);
+        assert!(e.closes.is_empty());
+        assert_eq!(e.actions, vec!["opened"]);
+    }
+
+    fn numbers(entries: &[Entry]) -> Vec<u64> {
+        entries.iter().filter_map(|e| e.number).collect()
+    }
+
+    #[test]
+    fn sorts_by_action_priority_then_number() {
+        let mut entries = vec![
+            entry(3, &["reviewed"]),
+            entry(1, &["pushed"]),
+            entry(4, &["opened", "authored & merged"]),
+            entry(2, &["reviewed"]),
+        ];
+        let priority = vec![String::from("authored & merged"), String::from("reviewed")];
+
+        sort_entries(&mut entries, &priority);
+        assert_eq!(numbers(&entries), vec![4, 2, 3, 1]);
+
+        sort_entries(&mut entries, &[]);
+        assert_eq!(numbers(&entries), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn merges_entries_of_several_fetches() {
+        let mut repos = HashMap::new();
+        repos.insert(String::from("a/b"), vec![entry(1, &["opened"])]);
+        let mut other = HashMap::new();
+        other.insert(
+            String::from("a/b"),
+            vec![entry(1, &["opened", "merged"]), entry(2, &["reviewed"])],
+        );
+        other.insert(String::from("c/d"), vec![entry(3, &["pushed"])]);
+
+        merge_repos(&mut repos, other);
+        assert_eq!(repos["a/b"][0].actions, vec!["opened", "merged"]);
+        assert_eq!(numbers(&repos["a/b"]), vec![1, 2]);
+        assert_eq!(numbers(&repos["c/d"]), vec![3]);
+    }
+
+    #[test]
+    fn links_jira_keys() {
+        assert_eq!(jira_keys("PROJ-123: fix login"), vec!["PROJ-123"]);
+        assert_eq!(
+            jira_keys("Fix (AB2-7, CD-8), see AB2-7"),
+            vec!["AB2-7", "CD-8"]
+        );
+        assert!(jira_keys("xPROJ-1 PROJ-1a PROJ- -1").is_empty());
+
+        let report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: Vec::new(),
+            repos: vec![(
+                String::from("org/a"),
+                vec![Entry {
+                    title: String::from("PROJ-123: fix login"),
+                    url: Some(String::from("https://github.com/org/a/pull/1")),
+                    ..entry(1, &["opened"])
+                }],
+            )]
+            .into_iter()
+            .collect(),
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+        let opts = RenderOptions {
+            jira_url: Some(String::from("https://jira.example.com/")),
+            ..Default::default()
+        };
+        let jira = "https://jira.example.com/browse/PROJ-123";
+        assert_eq!(
+            render(&Format::Text, &report, &opts),
+            format!(
+                "* org/a:\n  - [PR] (opened) PROJ-123: fix login https://github.com/org/a/pull/1 {}\n",
+                jira
+            )
+        );
+        assert!(render(&Format::Markdown, &report, &opts).contains(&format!(
+            "[PROJ-123: fix login](https://github.com/org/a/pull/1) [PROJ-123]({})",
+            jira
+        )));
+        assert!(render(&Format::SlackMrkdwn, &report, &opts)
+            .contains(&format!("> <{}|PROJ-123>", jira)));
+        assert!(
+            render(&Format::Confluence, &report, &opts).contains(&format!("] [PROJ-123|{}]", jira))
+        );
+        assert!(
+            render(&Format::HtmlStandalone, &report, &opts).contains(&format!(
+                "</a> <a class=\"jira\" href=\"{}\">PROJ-123</a>",
+                jira
+            ))
+        );
+    }
+
+    #[test]
+    fn time_ago_units() {
+        let now = Utc.ymd(2019, 8, 7).and_hms(12, 0, 0);
+        assert_eq!(time_ago(now, now), "just now");
+        assert_eq!(
+            time_ago(Utc.ymd(2019, 8, 7).and_hms(11, 59, 0), now),
+            "1 minute ago"
+        );
+        assert_eq!(
+            time_ago(Utc.ymd(2019, 8, 6).and_hms(18, 0, 0), now),
+            "18 hours ago"
+        );
+        assert_eq!(
+            time_ago(Utc.ymd(2019, 8, 5).and_hms(11, 0, 0), now),
+            "2 days ago"
+        );
+    }
+
+    #[test]
+    fn renders_text_with_themes() {
+        let mut repos = HashMap::new();
+        repos.insert(String::from("org/a"), vec![entry(1, &["authored"])]);
+        let report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: Vec::new(),
+            repos,
+            todo: vec![entry(2, &[])],
+            sort_repos: SortRepos::Alpha,
+        };
+        let render = |name: &str| {
+            let opts = RenderOptions {
+                theme: name.parse().unwrap(),
+                ..Default::default()
+            };
+            render_text(&report, &opts)
+        };
+        assert_eq!(
+            render("plain"),
+            "* org/a:\n  - [PR] (authored) PR 1 \n* To do:\n  - [PR] PR 2 \n"
+        );
+        assert_eq!(
+            render("minimal"),
+            "org/a:\n  [PR] (authored) PR 1 \nTo do:\n  [PR] PR 2 \n"
+        );
+        let boxed = render("boxed");
+        assert!(boxed.starts_with("■ org/a:\n│ • [PR] (authored) PR 1 \n─"));
+        assert!(boxed.ends_with("─\n■ To do:\n│ • [PR] PR 2 \n"));
+        assert!("fancy".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn renders_meetings_section() {
+        let mut report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: vec![Entry {
+                r#type: String::from("Meeting"),
+                title: String::from("Standup"),
+                ..Default::default()
+            }],
+            repos: HashMap::new(),
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+        let opts = RenderOptions {
+            empty_calendar: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_text(&report, &opts),
+            "* Meetings:\n  - [Meeting] Standup \n"
+        );
+        report.meetings.clear();
+        assert_eq!(
+            render_text(&report, &opts),
+            "* Meetings:\n  - (no meetings)\n"
+        );
+        assert_eq!(render_text(&report, &RenderOptions::default()), "");
+    }
+
+    #[test]
+    fn keeps_entries_within_hours() {
+        let at = |hour, number| Entry {
+            created_at: Some(
+                Local
+                    .ymd(2019, 8, 6)
+                    .and_hms(hour, 30, 0)
+                    .with_timezone(&Utc),
+            ),
+            ..entry(number, &[])
+        };
+        let report = |entries| {
+            let mut repos = HashMap::new();
+            repos.insert(String::from("org/a"), entries);
+            Report {
+                since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+                until: None,
+                meetings: Vec::new(),
+                repos,
+                todo: Vec::new(),
+                sort_repos: SortRepos::Alpha,
+            }
+        };
+        let hm = |h| NaiveTime::from_hms(h, 0, 0);
+
+        let mut r = report(vec![
+            at(8, 1),
+            at(9, 2),
+            at(17, 3),
+            at(23, 4),
+            entry(5, &[]),
+        ]);
+        r.retain_within_hours(hm(9), hm(18));
+        assert_eq!(numbers(&r.repos["org/a"]), vec![2, 3, 5]);
+
+        let mut r = report(vec![at(8, 1), at(12, 2), at(23, 3)]);
+        r.retain_within_hours(hm(22), hm(9));
+        assert_eq!(numbers(&r.repos["org/a"]), vec![1, 3]);
+
+        let mut r = report(vec![at(23, 1)]);
+        r.retain_within_hours(hm(9), hm(18));
+        assert!(r.repos.is_empty());
+    }
+
+    #[test]
+    fn splits_report_by_day() {
+        let at = |day, number| Entry {
+            created_at: Some(
+                Local
+                    .ymd(2019, 8, day)
+                    .and_hms(12, 0, 0)
+                    .with_timezone(&Utc),
+            ),
+            ..entry(number, &[])
+        };
+        let mut repos = HashMap::new();
+        repos.insert(
+            String::from("org/a"),
+            vec![at(6, 1), at(5, 2), entry(3, &[])],
+        );
+        repos.insert(String::from("org/b"), vec![at(6, 4)]);
+        let report = Report {
+            since: Utc.ymd(2019, 8, 5).and_hms(0, 0, 0),
+            until: None,
+            meetings: Vec::new(),
+            repos,
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+
+        let days: Vec<(Option<Date<Local>>, Vec<u64>)> = report
+            .split_by_day()
+            .into_iter()
+            .map(|(day, r)| {
+                let numbers = r
+                    .sorted_repos()
+                    .into_iter()
+                    .flat_map(|(_, entries)| numbers(entries))
+                    .collect();
+                (day, numbers)
+            })
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                (Some(Local.ymd(2019, 8, 5)), vec![2]),
+                (Some(Local.ymd(2019, 8, 6)), vec![1, 4]),
+                (None, vec![3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_markdown_links() {
+        let mut pr = entry(1, &["authored"]);
+        pr.title = String::from("Fix [flaky] test");
+        pr.url = Some(String::from("https://github.com/org/a/pull/1"));
+        let mut repos = HashMap::new();
+        repos.insert(String::from("org/a"), vec![pr]);
+        let report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: vec![Entry {
+                r#type: String::from("Meeting"),
+                title: String::from("Standup"),
+                ..Default::default()
+            }],
+            repos,
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+        assert_eq!(
+            render_markdown(&report, None),
+            "### Meetings\n\n- **Meeting** Standup\n\n\
+             ### org/a\n\n- **PR** (authored) \
+             [Fix \\[flaky\\] test](https://github.com/org/a/pull/1)\n"
+        );
+    }
+
+    #[test]
+    fn renders_json_document() {
+        let mut repos = HashMap::new();
+        repos.insert(String::from("org/a"), vec![entry(1, &["authored"])]);
+        let report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: vec![Entry {
+                r#type: String::from("Meeting"),
+                title: String::from("Standup"),
+                ..Default::default()
+            }],
+            repos,
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+        let doc: serde_json::Value = serde_json::from_str(&render_json(&report)).unwrap();
+        assert_eq!(doc["since"], "2019-08-06T00:00:00Z");
+        assert_eq!(doc["until"], serde_json::Value::Null);
+        assert_eq!(doc["meetings"][0]["title"], "Standup");
+        assert_eq!(doc["repos"]["org/a"][0]["number"], 1);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn renders_toml_with_repos_as_tables() {
+        let mut e = entry(1, &["authored"]);
+        e.reviews = Some(ReviewCounts {
+            approvals: 2,
+            changes_requested: 0,
+        });
+        let mut repos = HashMap::new();
+        repos.insert(String::from("org/a"), vec![e]);
+        let report = Report {
+            since: Utc.ymd(2019, 8, 6).and_hms(0, 0, 0),
+            until: None,
+            meetings: Vec::new(),
+            repos,
+            todo: Vec::new(),
+            sort_repos: SortRepos::Alpha,
+        };
+        let out = render_toml(&report);
+        assert!(out.contains("since = \"2019-08-06T00:00:00Z\"\n"));
+        assert!(out.contains("[[repos.\"org/a\"]]\n"));
+        assert!(out.contains("[repos.\"org/a\".reviews]\napprovals = 2\n"));
     }
 }